@@ -0,0 +1,189 @@
+use raylib::prelude::*;
+
+use serde::{Deserialize, Serialize};
+
+use crate::simulation::ShipInput;
+
+/// Only the first gamepad is used
+pub const GAMEPAD: i32 = 0;
+
+/// How analog sticks and triggers are turned into input
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct GamepadSettings {
+    /// Stick and trigger values below this are ignored (0.0..1.0)
+    pub dead_zone: f32,
+    /// Multiplier of values past the dead zone
+    pub sensitivity: f32,
+}
+
+impl Default for GamepadSettings {
+    fn default() -> Self {
+        GamepadSettings {
+            dead_zone: 0.15,
+            sensitivity: 1.0,
+        }
+    }
+}
+
+impl GamepadSettings {
+    /// Maps raw axis value (-1.0..=1.0) to input, taking dead zone and sensitivity into account
+    pub fn apply(&self, value: f32) -> f32 {
+        let dead_zone = self.dead_zone.clamp(0.0, 0.95);
+        let magnitude = ((value.abs() - dead_zone) / (1.0 - dead_zone)).max(0.0);
+        (magnitude * self.sensitivity).min(1.0) * value.signum()
+    }
+}
+
+/// Analog part of a gamepad. Can be read from raylib or made up (e.g. for testing)
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct GamepadState {
+    pub left_stick: Vector2,
+    pub right_stick: Vector2,
+    /// 0.0 when released, 1.0 when fully pressed
+    pub left_trigger: f32,
+    pub right_trigger: f32,
+}
+
+impl GamepadState {
+    /// State of the connected gamepad, if there's one
+    pub fn read(rl: &RaylibHandle) -> Option<Self> {
+        if !rl.is_gamepad_available(GAMEPAD) {
+            return None;
+        }
+        let axis = |axis| rl.get_gamepad_axis_movement(GAMEPAD, axis);
+        // Triggers go from -1.0 (released) to 1.0
+        let trigger = |axis| (axis + 1.0) * 0.5;
+        Some(GamepadState {
+            left_stick: rvec2(
+                axis(GamepadAxis::GAMEPAD_AXIS_LEFT_X),
+                axis(GamepadAxis::GAMEPAD_AXIS_LEFT_Y),
+            ),
+            right_stick: rvec2(
+                axis(GamepadAxis::GAMEPAD_AXIS_RIGHT_X),
+                axis(GamepadAxis::GAMEPAD_AXIS_RIGHT_Y),
+            ),
+            left_trigger: trigger(axis(GamepadAxis::GAMEPAD_AXIS_LEFT_TRIGGER)),
+            right_trigger: trigger(axis(GamepadAxis::GAMEPAD_AXIS_RIGHT_TRIGGER)),
+        })
+    }
+
+    /// Left stick strafes and thrusts, right trigger is the main engine,
+    /// left trigger the reverse one and right stick rotates
+    pub fn ship_input(&self, settings: &GamepadSettings) -> ShipInput {
+        let stick_x = settings.apply(self.left_stick.x);
+        let stick_y = settings.apply(self.left_stick.y);
+        let rotation = settings.apply(self.right_stick.x);
+        ShipInput {
+            // Stick up is negative
+            forward: (-stick_y).max(settings.apply(self.right_trigger)),
+            backward: stick_y.max(settings.apply(self.left_trigger)),
+            left: (-stick_x).max(0.0),
+            right: stick_x.max(0.0),
+            rotate_left: (-rotation).max(0.0),
+            rotate_right: rotation.max(0.0),
+            ..ShipInput::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-6
+    }
+
+    #[test]
+    fn dead_zone_is_ignored() {
+        let settings = GamepadSettings::default();
+        assert_eq!(settings.apply(0.0), 0.0);
+        assert_eq!(settings.apply(0.1), 0.0);
+        assert_eq!(settings.apply(-0.15), 0.0);
+        assert!(settings.apply(0.2) > 0.0);
+        assert!(close(settings.apply(1.0), 1.0));
+        assert!(close(settings.apply(-1.0), -1.0));
+    }
+
+    #[test]
+    fn values_past_dead_zone_are_rescaled_and_scaled_by_sensitivity() {
+        let mut settings = GamepadSettings {
+            dead_zone: 0.2,
+            sensitivity: 1.0,
+        };
+        // Halfway between the dead zone and full tilt
+        assert!(close(settings.apply(0.6), 0.5));
+        assert!(close(settings.apply(-0.6), -0.5));
+
+        settings.sensitivity = 2.0;
+        assert!(close(settings.apply(0.4), 0.5));
+        // Never more than full input
+        assert!(close(settings.apply(0.6), 1.0));
+        assert!(close(settings.apply(-1.0), -1.0));
+
+        settings.sensitivity = 0.5;
+        assert!(close(settings.apply(1.0), 0.5));
+    }
+
+    #[test]
+    fn sticks_and_triggers_map_to_thrust_and_rotation() {
+        let settings = GamepadSettings::default();
+        let input = |state: GamepadState| state.ship_input(&settings);
+
+        // Stick up is forward, down is backward
+        let up = input(GamepadState {
+            left_stick: rvec2(0.0, -1.0),
+            ..GamepadState::default()
+        });
+        assert!(close(up.forward, 1.0) && up.backward == 0.0);
+        let down = input(GamepadState {
+            left_stick: rvec2(0.0, 1.0),
+            ..GamepadState::default()
+        });
+        assert!(close(down.backward, 1.0) && down.forward == 0.0);
+
+        // Left stick sideways strafes
+        let left = input(GamepadState {
+            left_stick: rvec2(-1.0, 0.0),
+            ..GamepadState::default()
+        });
+        assert!(close(left.left, 1.0) && left.right == 0.0);
+        let right = input(GamepadState {
+            left_stick: rvec2(1.0, 0.0),
+            ..GamepadState::default()
+        });
+        assert!(close(right.right, 1.0) && right.left == 0.0);
+
+        // Right stick rotates
+        let rotate_left = input(GamepadState {
+            right_stick: rvec2(-1.0, 0.0),
+            ..GamepadState::default()
+        });
+        assert!(close(rotate_left.rotate_left, 1.0) && rotate_left.rotate_right == 0.0);
+        let rotate_right = input(GamepadState {
+            right_stick: rvec2(1.0, 0.0),
+            ..GamepadState::default()
+        });
+        assert!(close(rotate_right.rotate_right, 1.0) && rotate_right.rotate_left == 0.0);
+
+        // Triggers are the main and reverse engines
+        let triggers = input(GamepadState {
+            left_trigger: 1.0,
+            right_trigger: 1.0,
+            ..GamepadState::default()
+        });
+        assert!(close(triggers.forward, 1.0) && close(triggers.backward, 1.0));
+    }
+
+    #[test]
+    fn resting_gamepad_gives_no_input() {
+        let settings = GamepadSettings::default();
+        let drifting = GamepadState {
+            left_stick: rvec2(0.05, -0.1),
+            right_stick: rvec2(-0.1, 0.05),
+            left_trigger: 0.02,
+            right_trigger: 0.1,
+        };
+        assert_eq!(drifting.ship_input(&settings), ShipInput::default());
+    }
+}
@@ -0,0 +1,111 @@
+use raylib::prelude::KeyboardKey;
+use raylib::prelude::KeyboardKey::*;
+
+/// Names of keys as they're written in the controls file and shown in the game
+const KEY_NAMES: [(KeyboardKey, &str); 90] = [
+    (KEY_A, "A"),
+    (KEY_B, "B"),
+    (KEY_C, "C"),
+    (KEY_D, "D"),
+    (KEY_E, "E"),
+    (KEY_F, "F"),
+    (KEY_G, "G"),
+    (KEY_H, "H"),
+    (KEY_I, "I"),
+    (KEY_J, "J"),
+    (KEY_K, "K"),
+    (KEY_L, "L"),
+    (KEY_M, "M"),
+    (KEY_N, "N"),
+    (KEY_O, "O"),
+    (KEY_P, "P"),
+    (KEY_Q, "Q"),
+    (KEY_R, "R"),
+    (KEY_S, "S"),
+    (KEY_T, "T"),
+    (KEY_U, "U"),
+    (KEY_V, "V"),
+    (KEY_W, "W"),
+    (KEY_X, "X"),
+    (KEY_Y, "Y"),
+    (KEY_Z, "Z"),
+    (KEY_ZERO, "0"),
+    (KEY_ONE, "1"),
+    (KEY_TWO, "2"),
+    (KEY_THREE, "3"),
+    (KEY_FOUR, "4"),
+    (KEY_FIVE, "5"),
+    (KEY_SIX, "6"),
+    (KEY_SEVEN, "7"),
+    (KEY_EIGHT, "8"),
+    (KEY_NINE, "9"),
+    (KEY_F1, "F1"),
+    (KEY_F2, "F2"),
+    (KEY_F3, "F3"),
+    (KEY_F4, "F4"),
+    (KEY_F5, "F5"),
+    (KEY_F6, "F6"),
+    (KEY_F7, "F7"),
+    (KEY_F8, "F8"),
+    (KEY_F9, "F9"),
+    (KEY_F10, "F10"),
+    (KEY_F11, "F11"),
+    (KEY_F12, "F12"),
+    (KEY_SPACE, "Space"),
+    (KEY_ESCAPE, "Esc"),
+    (KEY_ENTER, "Enter"),
+    (KEY_TAB, "Tab"),
+    (KEY_BACKSPACE, "Backspace"),
+    (KEY_INSERT, "Insert"),
+    (KEY_DELETE, "Delete"),
+    (KEY_HOME, "Home"),
+    (KEY_END, "End"),
+    (KEY_PAGE_UP, "PageUp"),
+    (KEY_PAGE_DOWN, "PageDown"),
+    (KEY_UP, "Up"),
+    (KEY_DOWN, "Down"),
+    (KEY_LEFT, "Left"),
+    (KEY_RIGHT, "Right"),
+    (KEY_LEFT_SHIFT, "LeftShift"),
+    (KEY_RIGHT_SHIFT, "RightShift"),
+    (KEY_LEFT_CONTROL, "LeftCtrl"),
+    (KEY_RIGHT_CONTROL, "RightCtrl"),
+    (KEY_LEFT_ALT, "LeftAlt"),
+    (KEY_RIGHT_ALT, "RightAlt"),
+    (KEY_APOSTROPHE, "'"),
+    (KEY_COMMA, ","),
+    (KEY_MINUS, "-"),
+    (KEY_PERIOD, "."),
+    (KEY_SLASH, "/"),
+    (KEY_SEMICOLON, ";"),
+    (KEY_EQUAL, "="),
+    (KEY_LEFT_BRACKET, "["),
+    (KEY_RIGHT_BRACKET, "]"),
+    (KEY_BACKSLASH, "\\"),
+    (KEY_GRAVE, "`"),
+    (KEY_KP_0, "Num0"),
+    (KEY_KP_1, "Num1"),
+    (KEY_KP_2, "Num2"),
+    (KEY_KP_3, "Num3"),
+    (KEY_KP_4, "Num4"),
+    (KEY_KP_5, "Num5"),
+    (KEY_KP_6, "Num6"),
+    (KEY_KP_7, "Num7"),
+    (KEY_KP_8, "Num8"),
+    (KEY_KP_9, "Num9"),
+];
+
+pub fn key_name(key: KeyboardKey) -> &'static str {
+    KEY_NAMES
+        .iter()
+        .find(|(k, _)| *k == key)
+        .map(|(_, name)| *name)
+        .unwrap_or("?")
+}
+
+pub fn key_from_name(name: &str) -> Option<KeyboardKey> {
+    KEY_NAMES
+        .iter()
+        .find(|(_, n)| n.eq_ignore_ascii_case(name))
+        .map(|(key, _)| *key)
+}
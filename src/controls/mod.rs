@@ -0,0 +1,411 @@
+//! Actions the player can do and keys (and gamepad buttons) bound to them.
+//! Bindings are kept in `CONTROLS_PATH` and can be changed in the menu.
+
+use raylib::prelude::*;
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::CameraMode;
+use crate::simulation::ShipInput;
+
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fs;
+
+mod keys;
+pub use keys::{key_from_name, key_name};
+
+mod gamepad;
+pub use gamepad::{GamepadSettings, GamepadState, GAMEPAD};
+
+pub mod mouse;
+
+pub const CONTROLS_PATH: &str = "controls.ron";
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
+pub enum Action {
+    ThrustForward,
+    ThrustBackward,
+    StrafeLeft,
+    StrafeRight,
+    Afterburner,
+    Tether,
+    Fire,
+    Shield,
+    RotateLeft,
+    RotateRight,
+    RotationDamping,
+    CycleOrientation,
+    HoldVelocity,
+    ZoomIn,
+    ZoomOut,
+    CameraMode,
+    Rewind,
+    ReplaySlower,
+    ReplayFaster,
+    Pause,
+    Restart,
+    NewSeed,
+    Menu,
+    QuickSave,
+    QuickLoad,
+    ToggleBlur,
+    DebugOverlay,
+    DebugSolids,
+    DebugSensors,
+    DebugVelocities,
+    DebugGravity,
+    DebugContacts,
+    DebugSleep,
+}
+
+impl Action {
+    pub const ALL: [Action; 33] = [
+        Action::ThrustForward,
+        Action::ThrustBackward,
+        Action::StrafeLeft,
+        Action::StrafeRight,
+        Action::Afterburner,
+        Action::Tether,
+        Action::Fire,
+        Action::Shield,
+        Action::RotateLeft,
+        Action::RotateRight,
+        Action::RotationDamping,
+        Action::CycleOrientation,
+        Action::HoldVelocity,
+        Action::ZoomIn,
+        Action::ZoomOut,
+        Action::CameraMode,
+        Action::Rewind,
+        Action::ReplaySlower,
+        Action::ReplayFaster,
+        Action::Pause,
+        Action::Restart,
+        Action::NewSeed,
+        Action::Menu,
+        Action::QuickSave,
+        Action::QuickLoad,
+        Action::ToggleBlur,
+        Action::DebugOverlay,
+        Action::DebugSolids,
+        Action::DebugSensors,
+        Action::DebugVelocities,
+        Action::DebugGravity,
+        Action::DebugContacts,
+        Action::DebugSleep,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::ThrustForward => "Thrust forward",
+            Action::ThrustBackward => "Thrust backward",
+            Action::StrafeLeft => "Strafe left",
+            Action::StrafeRight => "Strafe right",
+            Action::Afterburner => "Afterburner",
+            Action::Tether => "Tether (hold)",
+            Action::Fire => "Fire",
+            Action::Shield => "Shield",
+            Action::RotateLeft => "Rotate left",
+            Action::RotateRight => "Rotate right",
+            Action::RotationDamping => "Rotation damping",
+            Action::CycleOrientation => "Auto orientation",
+            Action::HoldVelocity => "Hold velocity",
+            Action::ZoomIn => "Zoom in",
+            Action::ZoomOut => "Zoom out",
+            Action::CameraMode => "Camera mode",
+            Action::Rewind => "Rewind (hold)",
+            Action::ReplaySlower => "Replay slower",
+            Action::ReplayFaster => "Replay faster",
+            Action::Pause => "Pause",
+            Action::Restart => "Restart",
+            Action::NewSeed => "New level",
+            Action::Menu => "Back to menu",
+            Action::QuickSave => "Quick save",
+            Action::QuickLoad => "Quick load",
+            Action::ToggleBlur => "Toggle upscaling",
+            Action::DebugOverlay => "Debug overlay",
+            Action::DebugSolids => "Debug: solids",
+            Action::DebugSensors => "Debug: sensors",
+            Action::DebugVelocities => "Debug: velocity",
+            Action::DebugGravity => "Debug: gravity",
+            Action::DebugContacts => "Debug: contacts",
+            Action::DebugSleep => "Debug: sleep",
+        }
+    }
+
+    fn default_key(self) -> KeyboardKey {
+        match self {
+            Action::ThrustForward => KeyboardKey::KEY_W,
+            Action::ThrustBackward => KeyboardKey::KEY_S,
+            Action::StrafeLeft => KeyboardKey::KEY_A,
+            Action::StrafeRight => KeyboardKey::KEY_D,
+            Action::Afterburner => KeyboardKey::KEY_LEFT_SHIFT,
+            Action::Tether => KeyboardKey::KEY_SPACE,
+            Action::Fire => KeyboardKey::KEY_F,
+            Action::Shield => KeyboardKey::KEY_E,
+            Action::RotateLeft => KeyboardKey::KEY_I,
+            Action::RotateRight => KeyboardKey::KEY_O,
+            Action::RotationDamping => KeyboardKey::KEY_Z,
+            Action::CycleOrientation => KeyboardKey::KEY_X,
+            Action::HoldVelocity => KeyboardKey::KEY_V,
+            Action::ZoomIn => KeyboardKey::KEY_K,
+            Action::ZoomOut => KeyboardKey::KEY_L,
+            Action::CameraMode => KeyboardKey::KEY_M,
+            Action::Rewind => KeyboardKey::KEY_BACKSPACE,
+            Action::ReplaySlower => KeyboardKey::KEY_PAGE_DOWN,
+            Action::ReplayFaster => KeyboardKey::KEY_PAGE_UP,
+            Action::Pause => KeyboardKey::KEY_TAB,
+            Action::Restart => KeyboardKey::KEY_R,
+            Action::NewSeed => KeyboardKey::KEY_N,
+            Action::Menu => KeyboardKey::KEY_ESCAPE,
+            Action::QuickSave => KeyboardKey::KEY_F5,
+            Action::QuickLoad => KeyboardKey::KEY_F9,
+            Action::ToggleBlur => KeyboardKey::KEY_B,
+            Action::DebugOverlay => KeyboardKey::KEY_C,
+            Action::DebugSolids => KeyboardKey::KEY_ONE,
+            Action::DebugSensors => KeyboardKey::KEY_TWO,
+            Action::DebugVelocities => KeyboardKey::KEY_THREE,
+            Action::DebugGravity => KeyboardKey::KEY_FOUR,
+            Action::DebugContacts => KeyboardKey::KEY_FIVE,
+            Action::DebugSleep => KeyboardKey::KEY_SIX,
+        }
+    }
+
+    /// Gamepad button doing the same (thrust and rotation are analog, see `GamepadState`)
+    fn gamepad_button(self) -> Option<GamepadButton> {
+        match self {
+            Action::Afterburner => Some(GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_DOWN),
+            Action::Tether => Some(GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_RIGHT),
+            Action::Fire => Some(GamepadButton::GAMEPAD_BUTTON_RIGHT_THUMB),
+            Action::Shield => Some(GamepadButton::GAMEPAD_BUTTON_LEFT_THUMB),
+            Action::ZoomIn => Some(GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_UP),
+            Action::ZoomOut => Some(GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_DOWN),
+            Action::CameraMode => Some(GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_RIGHT),
+            Action::Rewind => Some(GamepadButton::GAMEPAD_BUTTON_LEFT_TRIGGER_1),
+            Action::RotationDamping => Some(GamepadButton::GAMEPAD_BUTTON_RIGHT_TRIGGER_1),
+            Action::CycleOrientation => Some(GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_LEFT),
+            Action::Pause => Some(GamepadButton::GAMEPAD_BUTTON_MIDDLE_RIGHT),
+            Action::Restart => Some(GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_UP),
+            Action::Menu => Some(GamepadButton::GAMEPAD_BUTTON_MIDDLE_LEFT),
+            _ => None,
+        }
+    }
+}
+
+/// Lines of the help text: actions and what they do together
+const HELP: [(&[Action], &str); 17] = [
+    (
+        &[
+            Action::ThrustForward,
+            Action::ThrustBackward,
+            Action::StrafeLeft,
+            Action::StrafeRight,
+        ],
+        "acceleration",
+    ),
+    (&[Action::Afterburner], "afterburner (heats the ship up)"),
+    (&[Action::Tether], "grappling tether (hold)"),
+    (&[Action::Fire], "shoot asteroids (heats the ship up)"),
+    (&[Action::Shield], "shield (takes one hit)"),
+    (&[Action::RotateLeft, Action::RotateRight], "rotation"),
+    (
+        &[
+            Action::RotationDamping,
+            Action::CycleOrientation,
+            Action::HoldVelocity,
+        ],
+        "flight assists",
+    ),
+    (&[Action::ZoomIn, Action::ZoomOut], "zoom in/out"),
+    (&[Action::CameraMode], "switch camera mode"),
+    (&[Action::Pause], "pause/unpause"),
+    (&[Action::Rewind], "rewind (hold)"),
+    (
+        &[Action::ReplaySlower, Action::ReplayFaster],
+        "replay speed (drag to move the camera)",
+    ),
+    (&[Action::QuickSave, Action::QuickLoad], "quick save/load"),
+    (&[Action::Restart, Action::NewSeed], "restart/new level"),
+    (&[Action::Menu], "back to menu"),
+    (&[Action::ToggleBlur], "toggle upscaling"),
+    (
+        &[
+            Action::DebugOverlay,
+            Action::DebugSolids,
+            Action::DebugSensors,
+            Action::DebugVelocities,
+            Action::DebugGravity,
+            Action::DebugContacts,
+            Action::DebugSleep,
+        ],
+        "physics debug overlay and its layers",
+    ),
+];
+
+/// What's saved in `CONTROLS_PATH`
+#[derive(Serialize, Deserialize)]
+struct ControlsFile {
+    /// Names of keys (see `keys.rs`)
+    keys: BTreeMap<Action, String>,
+    #[serde(default)]
+    gamepad: GamepadSettings,
+    #[serde(default)]
+    mouse_steering: bool,
+    #[serde(default)]
+    camera_mode: CameraMode,
+}
+
+/// Keys bound to actions
+#[derive(Clone)]
+pub struct Bindings {
+    keys: HashMap<Action, KeyboardKey>,
+    pub gamepad: GamepadSettings,
+    /// Ship turns toward the cursor, mouse buttons fire thrusters and wheel zooms
+    pub mouse_steering: bool,
+    pub camera_mode: CameraMode,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        let keys = Action::ALL
+            .iter()
+            .map(|action| (*action, action.default_key()))
+            .collect();
+        Bindings {
+            keys,
+            gamepad: GamepadSettings::default(),
+            mouse_steering: false,
+            camera_mode: CameraMode::default(),
+        }
+    }
+}
+
+impl Bindings {
+    /// Loads bindings from `CONTROLS_PATH`, actions missing there keep default keys
+    pub fn load() -> Self {
+        let mut bindings = Self::default();
+        let text = match fs::read_to_string(CONTROLS_PATH) {
+            Ok(text) => text,
+            // No file yet, defaults it is
+            Err(_) => return bindings,
+        };
+        match ron::from_str::<ControlsFile>(&text) {
+            Ok(file) => {
+                bindings.gamepad = file.gamepad;
+                bindings.mouse_steering = file.mouse_steering;
+                bindings.camera_mode = file.camera_mode;
+                for (action, name) in file.keys {
+                    match key_from_name(&name) {
+                        Some(key) => bindings.set(action, key),
+                        None => println!("Unknown key \"{}\" for {:?}", name, action),
+                    }
+                }
+            }
+            Err(err) => println!("Couldn't read {}: {}", CONTROLS_PATH, err),
+        }
+        bindings
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        let keys = self
+            .keys
+            .iter()
+            .map(|(action, key)| (*action, key_name(*key).to_string()))
+            .collect();
+        let file = ControlsFile {
+            keys,
+            gamepad: self.gamepad,
+            mouse_steering: self.mouse_steering,
+            camera_mode: self.camera_mode,
+        };
+        let text = ron::ser::to_string_pretty(&file, ron::ser::PrettyConfig::default())?;
+        fs::write(CONTROLS_PATH, text)?;
+        Ok(())
+    }
+
+    pub fn key(&self, action: Action) -> KeyboardKey {
+        self.keys
+            .get(&action)
+            .copied()
+            .unwrap_or_else(|| action.default_key())
+    }
+
+    pub fn set(&mut self, action: Action, key: KeyboardKey) {
+        self.keys.insert(action, key);
+    }
+
+    pub fn is_down(&self, rl: &RaylibHandle, action: Action) -> bool {
+        let button_down = action
+            .gamepad_button()
+            .map(|button| rl.is_gamepad_button_down(GAMEPAD, button))
+            .unwrap_or(false);
+        rl.is_key_down(self.key(action)) || button_down
+    }
+
+    pub fn is_pressed(&self, rl: &RaylibHandle, action: Action) -> bool {
+        let button_pressed = action
+            .gamepad_button()
+            .map(|button| rl.is_gamepad_button_pressed(GAMEPAD, button))
+            .unwrap_or(false);
+        rl.is_key_pressed(self.key(action)) || button_pressed
+    }
+
+    /// Ship controls from keyboard (and mouse buttons) and `gamepad` together
+    pub fn ship_input(&self, rl: &RaylibHandle, gamepad: Option<&GamepadState>) -> ShipInput {
+        let value = |action| self.value(rl, action);
+        let mouse = |button| {
+            if self.mouse_steering && rl.is_mouse_button_down(button) {
+                1.0
+            } else {
+                0.0
+            }
+        };
+        let keyboard = ShipInput {
+            forward: value(Action::ThrustForward).max(mouse(MouseButton::MOUSE_LEFT_BUTTON)),
+            backward: value(Action::ThrustBackward).max(mouse(MouseButton::MOUSE_RIGHT_BUTTON)),
+            left: value(Action::StrafeLeft),
+            right: value(Action::StrafeRight),
+            rotate_left: value(Action::RotateLeft),
+            rotate_right: value(Action::RotateRight),
+            boost: self.is_down(rl, Action::Afterburner),
+            tether: self.is_down(rl, Action::Tether),
+            fire: self.is_down(rl, Action::Fire),
+            shield: self.is_down(rl, Action::Shield),
+            ..ShipInput::default()
+        };
+        let analog = match gamepad {
+            Some(gamepad) => gamepad.ship_input(&self.gamepad),
+            None => return keyboard,
+        };
+        // Whichever is pushed more
+        ShipInput {
+            forward: keyboard.forward.max(analog.forward),
+            backward: keyboard.backward.max(analog.backward),
+            left: keyboard.left.max(analog.left),
+            right: keyboard.right.max(analog.right),
+            rotate_left: keyboard.rotate_left.max(analog.rotate_left),
+            rotate_right: keyboard.rotate_right.max(analog.rotate_right),
+            ..keyboard
+        }
+    }
+
+    /// 1.0 if the action's key is held, 0.0 otherwise
+    pub fn value(&self, rl: &RaylibHandle, action: Action) -> f32 {
+        if self.is_down(rl, action) {
+            1.0
+        } else {
+            0.0
+        }
+    }
+
+    /// List of controls for the help popup
+    pub fn help_text(&self) -> String {
+        let mut text = String::from("Controls:\n");
+        for (actions, description) in HELP.iter() {
+            let keys: Vec<&str> = actions.iter().map(|a| key_name(self.key(*a))).collect();
+            text += &format!("  {} - {}\n", keys.join(" "), description);
+        }
+        text
+    }
+}
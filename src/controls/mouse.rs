@@ -0,0 +1,19 @@
+use crate::math::NVector2;
+use crate::simulation::{turn_toward, ShipInput};
+
+/// How much one notch of the mouse wheel zooms
+pub const WHEEL_ZOOM: f32 = 3.0;
+
+/// Sets rotation of `input` so the ship turns toward `direction` (in world space).
+/// Ship's nose points along -Y when its angle is 0
+pub fn steer(input: ShipInput, angle: f32, angvel: f32, direction: NVector2) -> ShipInput {
+    if direction.norm_squared() < 1.0 {
+        return input;
+    }
+    let command = turn_toward(angle, angvel, direction);
+    ShipInput {
+        rotate_left: (-command).max(0.0),
+        rotate_right: command.max(0.0),
+        ..input
+    }
+}
@@ -0,0 +1,130 @@
+//! Camera following the player's ship in one of several modes
+
+use crate::math::{lerp, to_nv2, to_rv2, NVector2};
+
+use raylib::prelude::*;
+
+use serde::{Deserialize, Serialize};
+
+/// How quickly the camera catches up with where it should be (per second,
+/// 10.0 is close to the old 0.15 per frame at 60 fps)
+const FOLLOW_RATE: f32 = 10.0;
+/// How quickly rotation catches up (except in ship-relative mode, which is exact)
+const ROTATE_RATE: f32 = 5.0;
+/// How quickly automatic zoom changes
+const ZOOM_RATE: f32 = 2.0;
+/// Camera looks ahead by where the ship will be in this many seconds
+const LOOKAHEAD_TIME: f32 = 0.3;
+/// But never further than this
+const MAX_LOOKAHEAD: f32 = 500.0;
+/// At this speed the view is zoomed out to half
+const HALF_ZOOM_SPEED: f32 = 2500.0;
+/// Automatic zoom doesn't go further out than this (times the player's zoom)
+const MIN_AUTO_ZOOM: f32 = 0.5;
+/// Space around the ship and the gate when framing both
+const FRAME_MARGIN: f32 = 600.0;
+/// Velocity-aligned view keeps its rotation below this speed
+const MIN_ALIGN_SPEED: f32 = 50.0;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum CameraMode {
+    /// Rotates with the ship (nose is always up)
+    ShipRelative,
+    /// Never rotates
+    NorthUp,
+    /// Direction of flight is up
+    VelocityAligned,
+    /// Zooms out so the ship and the next gate are both in view
+    FrameGate,
+}
+
+impl Default for CameraMode {
+    fn default() -> Self {
+        CameraMode::ShipRelative
+    }
+}
+
+impl CameraMode {
+    /// Next one when switching with one key
+    pub fn next(self) -> Self {
+        match self {
+            CameraMode::ShipRelative => CameraMode::NorthUp,
+            CameraMode::NorthUp => CameraMode::VelocityAligned,
+            CameraMode::VelocityAligned => CameraMode::FrameGate,
+            CameraMode::FrameGate => CameraMode::ShipRelative,
+        }
+    }
+}
+
+/// What the camera follows this frame
+pub struct CameraFocus {
+    pub position: NVector2,
+    pub velocity: NVector2,
+    /// Ship's rotation (radians)
+    pub rotation: f32,
+    /// Zoom the player chose
+    pub zoom: f32,
+    /// Position of the gate to fly through next
+    pub next_gate: Option<NVector2>,
+    /// View mustn't rotate (e.g. for cursor steering)
+    pub steady: bool,
+}
+
+/// Moves a `Camera2D` smoothly (independent of frame rate)
+pub struct ShipCamera {
+    pub mode: CameraMode,
+    /// Automatic zoom (multiplies the player's zoom)
+    auto_zoom: f32,
+}
+
+impl ShipCamera {
+    pub fn new(mode: CameraMode) -> Self {
+        ShipCamera {
+            mode,
+            auto_zoom: 1.0,
+        }
+    }
+
+    pub fn update(&mut self, camera: &mut Camera2D, focus: &CameraFocus, delta: f32) {
+        // Share of the remaining distance covered this frame
+        let smoothing = |rate: f32| 1.0 - (-rate * delta).exp();
+
+        // Where to look and how far to zoom out
+        let lookahead = focus.velocity * LOOKAHEAD_TIME;
+        let lookahead = lookahead * (MAX_LOOKAHEAD / lookahead.norm().max(MAX_LOOKAHEAD));
+        let speed_zoom = (1.0 / (1.0 + focus.velocity.norm() / HALF_ZOOM_SPEED)).max(MIN_AUTO_ZOOM);
+        let (target, auto_zoom) = match (self.mode, focus.next_gate) {
+            (CameraMode::FrameGate, Some(gate)) => {
+                // Half of the visible area (in world units at zoom 1.0)
+                let half_view = camera.offset.x.min(camera.offset.y);
+                let half_extent = (gate - focus.position).norm() / 2.0 + FRAME_MARGIN;
+                let fit = half_view / half_extent / focus.zoom;
+                ((focus.position + gate) / 2.0, fit.min(speed_zoom))
+            }
+            _ => (focus.position + lookahead, speed_zoom),
+        };
+
+        camera.target = to_rv2(lerp(to_nv2(camera.target), target, smoothing(FOLLOW_RATE)));
+        self.auto_zoom = lerp(self.auto_zoom, auto_zoom, smoothing(ZOOM_RATE));
+        camera.zoom = focus.zoom * self.auto_zoom;
+
+        // Rotation (in degrees, the ship's nose is -Y)
+        let rotation = match self.mode {
+            _ if focus.steady => 0.0,
+            CameraMode::ShipRelative => {
+                camera.rotation = -focus.rotation * RAD2DEG as f32;
+                return;
+            }
+            CameraMode::NorthUp | CameraMode::FrameGate => 0.0,
+            CameraMode::VelocityAligned => {
+                if focus.velocity.norm() < MIN_ALIGN_SPEED {
+                    return;
+                }
+                -focus.velocity.x.atan2(-focus.velocity.y) * RAD2DEG as f32
+            }
+        };
+        // Shortest way around
+        let difference = (rotation - camera.rotation + 540.0).rem_euclid(360.0) - 180.0;
+        camera.rotation += difference * smoothing(ROTATE_RATE);
+    }
+}
@@ -0,0 +1,243 @@
+use crate::controls::{key_name, Action, Bindings};
+use crate::math::{to_rv2, NPoint2};
+use crate::simulation::Simulation;
+
+use rapier2d::prelude::*;
+use raylib::prelude::*;
+
+const SOLID_COLOR: Color = Color {
+    r: 70,
+    g: 200,
+    b: 70,
+    a: 200,
+};
+const SENSOR_COLOR: Color = Color {
+    r: 230,
+    g: 210,
+    b: 60,
+    a: 200,
+};
+const SLEEPING_COLOR: Color = Color {
+    r: 130,
+    g: 130,
+    b: 150,
+    a: 200,
+};
+const VELOCITY_COLOR: Color = Color {
+    r: 80,
+    g: 160,
+    b: 255,
+    a: 220,
+};
+const GRAVITY_COLOR: Color = Color {
+    r: 230,
+    g: 80,
+    b: 200,
+    a: 220,
+};
+const CONTACT_COLOR: Color = Color {
+    r: 255,
+    g: 60,
+    b: 60,
+    a: 255,
+};
+
+const LINE_THICKNESS: f32 = 3.0;
+/// Velocity vectors show where a body will be in this many seconds
+const VELOCITY_SCALE: f32 = 0.5;
+/// Gravity vectors show the velocity gravity adds in this many seconds
+const GRAVITY_SCALE: f32 = 1.0;
+
+/// Physics debug overlay, every layer can be toggled separately
+pub struct DebugDraw {
+    pub enabled: bool,
+    /// Shapes of solid colliders
+    pub solids: bool,
+    /// Shapes of sensors (gate areas)
+    pub sensors: bool,
+    pub velocities: bool,
+    pub gravity: bool,
+    pub contacts: bool,
+    /// Colors sleeping bodies differently
+    pub sleep: bool,
+}
+
+impl DebugDraw {
+    pub fn new() -> Self {
+        DebugDraw {
+            enabled: false,
+            solids: true,
+            sensors: true,
+            velocities: false,
+            gravity: false,
+            contacts: false,
+            sleep: false,
+        }
+    }
+
+    /// Toggles the overlay and its layers with the keys bound to them
+    pub fn process(&mut self, rl: &RaylibHandle, bindings: &Bindings) {
+        if bindings.is_pressed(rl, Action::DebugOverlay) {
+            self.enabled ^= true;
+        }
+        if !self.enabled {
+            return;
+        }
+        for (action, layer) in self.layers_mut().iter_mut() {
+            if bindings.is_pressed(rl, *action) {
+                **layer ^= true;
+            }
+        }
+    }
+
+    /// Text listing the layers and their keys, for the HUD
+    pub fn legend(&self, bindings: &Bindings) -> String {
+        let layers = [
+            (Action::DebugSolids, "solids", self.solids),
+            (Action::DebugSensors, "sensors", self.sensors),
+            (Action::DebugVelocities, "velocity", self.velocities),
+            (Action::DebugGravity, "gravity", self.gravity),
+            (Action::DebugContacts, "contacts", self.contacts),
+            (Action::DebugSleep, "sleep", self.sleep),
+        ];
+        layers
+            .iter()
+            .map(|(action, name, on)| {
+                let check = if *on { "x" } else { " " };
+                format!("[{}] {} {}", check, key_name(bindings.key(*action)), name)
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    fn layers_mut(&mut self) -> [(Action, &mut bool); 6] {
+        [
+            (Action::DebugSolids, &mut self.solids),
+            (Action::DebugSensors, &mut self.sensors),
+            (Action::DebugVelocities, &mut self.velocities),
+            (Action::DebugGravity, &mut self.gravity),
+            (Action::DebugContacts, &mut self.contacts),
+            (Action::DebugSleep, &mut self.sleep),
+        ]
+    }
+
+    /// Draws the enabled layers (in world space)
+    pub fn draw(&self, d: &mut impl RaylibDraw, sim: &Simulation) {
+        if !self.enabled {
+            return;
+        }
+
+        for (_, collider) in sim.colliders().iter() {
+            let is_sensor = collider.is_sensor();
+            if (is_sensor && !self.sensors) || (!is_sensor && !self.solids) {
+                continue;
+            }
+            let sleeping = collider
+                .parent()
+                .map(|body| sim.bodies()[body].is_sleeping())
+                .unwrap_or(false);
+            let color = if is_sensor {
+                SENSOR_COLOR
+            } else if self.sleep && sleeping {
+                SLEEPING_COLOR
+            } else {
+                SOLID_COLOR
+            };
+            draw_collider(d, collider, color);
+        }
+
+        if self.velocities || self.gravity {
+            for (_, body) in sim.bodies().iter() {
+                if !body.is_dynamic() {
+                    continue;
+                }
+                let position = *body.translation();
+                if self.velocities {
+                    let end = position + body.linvel() * VELOCITY_SCALE;
+                    draw_arrow(d, to_rv2(position), to_rv2(end), VELOCITY_COLOR);
+                }
+                if self.gravity {
+                    let end = position + sim.gravity(position) * GRAVITY_SCALE;
+                    draw_arrow(d, to_rv2(position), to_rv2(end), GRAVITY_COLOR);
+                }
+            }
+        }
+
+        if self.contacts {
+            for (point, normal) in sim.contact_points() {
+                let point = to_rv2(point.coords);
+                d.draw_circle_v(point, 6.0, CONTACT_COLOR);
+                let end = point + to_rv2(normal) * 30.0;
+                d.draw_line_ex(point, end, LINE_THICKNESS, CONTACT_COLOR);
+            }
+        }
+    }
+}
+
+/// Draws the outline of the collider's actual shape at its world position
+fn draw_collider(d: &mut impl RaylibDraw, collider: &Collider, color: Color) {
+    let position = collider.position();
+    let to_world = |p: &NPoint2| to_rv2((position * p).coords);
+    let shape = collider.shape();
+
+    if let Some(ball) = shape.as_ball() {
+        let center = to_rv2(position.translation.vector);
+        draw_circle_outline(d, center, ball.radius, color);
+        // Show rotation
+        let edge = to_world(&point![ball.radius, 0.]);
+        d.draw_line_ex(center, edge, LINE_THICKNESS, color);
+    } else if let Some(capsule) = shape.as_capsule() {
+        let a = to_world(&capsule.segment.a);
+        let b = to_world(&capsule.segment.b);
+        draw_circle_outline(d, a, capsule.radius, color);
+        draw_circle_outline(d, b, capsule.radius, color);
+        let dir = (b - a).normalized();
+        let side = Vector2::new(-dir.y, dir.x) * capsule.radius;
+        d.draw_line_ex(a + side, b + side, LINE_THICKNESS, color);
+        d.draw_line_ex(a - side, b - side, LINE_THICKNESS, color);
+    } else if let Some(cuboid) = shape.as_cuboid() {
+        let (w, h) = (cuboid.half_extents.x, cuboid.half_extents.y);
+        let corners: Vec<Vector2> = [point![-w, -h], point![w, -h], point![w, h], point![-w, h]]
+            .iter()
+            .map(to_world)
+            .collect();
+        draw_outline(d, &corners, color);
+    } else if let Some(polygon) = shape.as_convex_polygon() {
+        let points: Vec<Vector2> = polygon.points().iter().map(to_world).collect();
+        draw_outline(d, &points, color);
+    } else {
+        // Shapes the game doesn't use, at least show where they are
+        let aabb = collider.compute_aabb();
+        let corners: Vec<Vector2> = aabb.vertices().iter().map(|p| to_rv2(p.coords)).collect();
+        draw_outline(d, &corners, color);
+    }
+}
+
+fn draw_circle_outline(d: &mut impl RaylibDraw, center: Vector2, radius: f32, color: Color) {
+    d.draw_ring(
+        center,
+        radius - LINE_THICKNESS / 2.0,
+        radius + LINE_THICKNESS / 2.0,
+        0.,
+        360.,
+        36,
+        color,
+    );
+}
+
+fn draw_outline(d: &mut impl RaylibDraw, points: &[Vector2], color: Color) {
+    for (i, a) in points.iter().enumerate() {
+        let b = points[(i + 1) % points.len()];
+        d.draw_line_ex(*a, b, LINE_THICKNESS, color);
+    }
+}
+
+fn draw_arrow(d: &mut impl RaylibDraw, start: Vector2, end: Vector2, color: Color) {
+    if (end - start).length() < 1.0 {
+        return;
+    }
+    d.draw_line_ex(start, end, LINE_THICKNESS, color);
+    let back = (start - end).normalized() * 15.0;
+    d.draw_line_ex(end, end + back.rotated(0.5), LINE_THICKNESS, color);
+    d.draw_line_ex(end, end + back.rotated(-0.5), LINE_THICKNESS, color);
+}
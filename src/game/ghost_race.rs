@@ -0,0 +1,85 @@
+//! Time attack: records the run and races it against the best one so far
+//! (or a ghost file dropped onto the window)
+
+use crate::simulation::{Ghost, GhostFrame, Simulation};
+
+/// Seconds a split stays on screen after passing a gate
+const SPLIT_SHOW_TIME: f32 = 3.0;
+
+pub struct GhostRace {
+    /// The run being flown
+    recording: Ghost,
+    /// Personal best on this level
+    best: Option<Ghost>,
+    /// Ghost flying along (the personal best unless another one was loaded)
+    rival: Option<Ghost>,
+    /// Time behind the rival at the last gate (negative is ahead)
+    /// and seconds left to show it
+    split: Option<(f32, f32)>,
+}
+
+impl GhostRace {
+    /// Starts recording and loads the personal best for the level `sim` is on
+    pub fn new(sim: &Simulation) -> Self {
+        let best = Ghost::load(&Ghost::best_path(sim))
+            .ok()
+            .filter(|ghost| ghost.fits(sim));
+        GhostRace {
+            recording: Ghost::new(sim),
+            rival: best.clone(),
+            best,
+            split: None,
+        }
+    }
+
+    /// Races against the ghost in the file at `path` instead
+    pub fn load_rival(&mut self, path: &str, sim: &Simulation) {
+        match Ghost::load(path) {
+            Ok(ghost) if ghost.fits(sim) => self.rival = Some(ghost),
+            Ok(_) => println!("Ghost {} is from a different level", path),
+            Err(err) => println!("Couldn't load ghost {}: {}", path, err),
+        }
+    }
+
+    /// Has to be called after every simulation step. Saves the run once it beats
+    /// the personal best
+    pub fn record(&mut self, sim: &Simulation) {
+        self.recording.record(sim);
+        if self.recording.beats(self.best.as_ref()) {
+            if let Err(err) = self.recording.save(&Ghost::best_path(sim)) {
+                println!("Couldn't save ghost: {}", err);
+            }
+            self.best = Some(self.recording.clone());
+        }
+    }
+
+    /// Compares the time `gate` was passed at with the rival's
+    pub fn gate_passed(&mut self, gate: u32) {
+        let rival = self.rival.as_ref().and_then(|rival| rival.split(gate));
+        if let (Some(time), Some(rival)) = (self.recording.split(gate), rival) {
+            self.split = Some((time - rival, SPLIT_SHOW_TIME));
+        }
+    }
+
+    pub fn process(&mut self, delta: f32) {
+        if let Some((_, timer)) = self.split.as_mut() {
+            *timer -= delta;
+        }
+        self.split = self.split.filter(|(_, timer)| *timer > 0.0);
+    }
+
+    /// Time behind the rival at the last gate (while it's shown)
+    pub fn split(&self) -> Option<f32> {
+        self.split.map(|(difference, _)| difference)
+    }
+
+    /// Finishing time of the rival
+    pub fn rival_time(&self) -> Option<f32> {
+        self.rival.as_ref().and_then(|rival| rival.time())
+    }
+
+    /// Where the rival was at `time`
+    pub fn rival_frame(&self, time: f32) -> Option<GhostFrame> {
+        self.rival.as_ref().and_then(|rival| rival.frame_at(time))
+    }
+}
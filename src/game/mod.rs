@@ -1,73 +1,93 @@
-use crate::math::{lerp, to_nv2, to_rv2, NVector2};
-use crate::SHIP_NAMES;
+use crate::controls::{key_name, mouse, Action, Bindings, GamepadState};
+use crate::math::{to_nv2, to_rv2, NVector2};
+use crate::ruleset::Objective;
+use crate::ships::ShipDef;
+use crate::simulation::{
+    Orientation, Refuelling, Replay, Rewind, ShipInput, SimEvent, Simulation, Snapshot,
+    ORBIT_MAX_ALTITUDE, ORBIT_MIN_ALTITUDE, PAD_HALF_WIDTH, STATION_DOCK_RADIUS, TIMESTEP,
+};
 use rapier2d::prelude::*;
 use raylib::prelude::*;
-use std::cell::RefCell;
-use std::rc::Rc;
 
 use crate::game_object::*;
 
-mod physics_server;
-use physics_server::*;
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use rand::prelude::*;
-use rand_pcg::Pcg64;
+mod debug_draw;
+use debug_draw::DebugDraw;
 
-use std::f32::consts::PI;
+mod playback;
+use playback::Playback;
 
-use std::collections::HashMap;
+mod ghost_race;
+use ghost_race::GhostRace;
+
+mod camera;
+pub use camera::CameraMode;
+use camera::{CameraFocus, ShipCamera};
 
 #[macro_export]
 macro_rules! DrawHandle {
     () =>  { RaylibShaderMode<RaylibMode2D<RaylibTextureMode<RaylibDrawHandle>>> }
 }
 
-/// Color of debug collider
-const COLL_COLOR: Color = Color {
-    r: 70,
-    g: 200,
-    b: 70,
-    a: 130,
-};
+const RENDER_DISTANCE: f32 = 12000i32.pow(2) as f32;
 
-const G: f32 = 10.0;
+/// Simulation won't try to catch up more than this many steps in one frame
+const MAX_STEPS_PER_FRAME: u32 = 5;
 
-const RENDER_DISTANCE: f32 = 12000i32.pow(2) as f32;
+const QUICKSAVE_PATH: &str = "quicksave.bin";
+/// Every run is saved here
+const REPLAYS_DIR: &str = "replays";
+
+/// Color of everything giving fuel
+const FUEL_COLOR: Color = Color {
+    r: 90,
+    g: 220,
+    b: 120,
+    a: 255,
+};
 
 pub struct Game<'a> {
     rl: &'a mut RaylibHandle,
     thread: &'a RaylibThread,
     audio: &'a mut RaylibAudio,
-    rng: Pcg64,
-    fuel_mode: bool,
+    sim: Simulation,
+    /// Definition of the player's ship (for drawing it)
+    ship: ShipDef,
+    bindings: Bindings,
     draw_fps: bool,
-    draw_collisions: bool,
+    debug_draw: DebugDraw,
     blur: bool,
     bg_color: Color,
-    physics_server: PhysicsServer,
-    rigid_body_set: RigidBodySet,
-    collider_set: ColliderSet,
-    process_objects: HashMap<u128, Rc<RefCell<dyn Processing>>>,
-    draw_objects: HashMap<u128, Rc<RefCell<dyn Drawable>>>,
-    phys_objects: HashMap<u128, Rc<RefCell<dyn PhysicsObject>>>,
-    planet_objects: HashMap<u128, Rc<RefCell<Planet>>>,
-    asteroid_colliders: HashMap<ColliderHandle, u128>,
-    gate_objects: Vec<Rc<RefCell<Gate>>>,
-    player_rc: Option<Rc<RefCell<Player>>>,
+    planet_objects: Vec<Planet>,
+    gate_objects: Vec<Gate>,
+    /// Drawables of asteroids (pooled ones included, so they can be reused)
+    asteroid_objects: HashMap<ColliderHandle, Asteroid>,
+    player: Option<Player>,
     player_tex: WeakTexture2D,
     exhaust_tex: WeakTexture2D,
-    player_score: i32,
-    time_since_start: f32,
-    asteroid_spawn_timer: f32,
-    completed: bool,
+    /// Frame time not simulated yet (simulation runs in fixed steps)
+    step_accumulator: f32,
+    /// Last state saved with F5 (and what rewinding had cost by then)
+    quicksave: Option<(Snapshot, i32)>,
+    rewind: Rewind,
+    /// Run being recorded (or watched)
+    replay: Replay,
+    /// Watching a replay (`None` when playing)
+    playback: Option<Playback>,
+    /// Time attack (`None` when it's off)
+    ghost_race: Option<GhostRace>,
+    /// Drawable of the ghost ship
+    ghost_object: GameObject,
     camera: Camera2D,
+    ship_camera: ShipCamera,
     font: Font,
     asteroid_tex: WeakTexture2D,
     gate_tex: WeakTexture2D,
     gate_off_tex: WeakTexture2D,
     gate_darker_tex: WeakTexture2D,
-    gate_count: u32,
-    next_gate: u32,
     arrow: GameObject,
     arrow_tex: WeakTexture2D,
     planet_shader: Shader,
@@ -91,12 +111,14 @@ impl<'a> Game<'a> {
         audio: &'a mut RaylibAudio,
         window_width: i16,
         window_height: i16,
-        seed: u64,
-        fuel_mode: bool,
-        selected_ship: usize,
+        replay: Replay,
+        ship: ShipDef,
+        bindings: Bindings,
+        time_attack: bool,
+        watch: bool,
     ) -> Self {
+        let sim = replay.start();
         let draw_fps = true;
-        let draw_collisions = false;
         let blur = true;
 
         let font = rl
@@ -108,11 +130,10 @@ impl<'a> Game<'a> {
             )
             .unwrap();
 
-        let ship_name = SHIP_NAMES[selected_ship];
         let player_tex = unsafe {
             rl.load_texture(
                 thread,
-                &("resources/textures/ships/".to_owned() + ship_name + ".png"),
+                &("resources/textures/ships/".to_owned() + &ship.texture + ".png"),
             )
             .unwrap()
             .make_weak()
@@ -174,17 +195,6 @@ impl<'a> Game<'a> {
 
         let bg_color = color::rcolor(47, 40, 70, 255);
 
-        // Initialize physics
-        let rigid_body_set = RigidBodySet::new();
-        let collider_set = ColliderSet::new();
-
-        let process_objects: HashMap<u128, Rc<RefCell<dyn Processing>>> = HashMap::new();
-        let draw_objects: HashMap<u128, Rc<RefCell<dyn Drawable>>> = HashMap::new();
-        let phys_objects: HashMap<u128, Rc<RefCell<dyn PhysicsObject>>> = HashMap::new();
-        let planet_objects: HashMap<u128, Rc<RefCell<Planet>>> = HashMap::new();
-        let gate_objects: Vec<Rc<RefCell<Gate>>> = Vec::new();
-        let asteroid_colliders: HashMap<ColliderHandle, u128> = HashMap::new();
-
         let camera = Camera2D {
             offset: rvec2(window_width as f32 / 2.0, window_height as f32 / 2.0) * 2.0,
             target: rvec2(0., 0.),
@@ -192,9 +202,26 @@ impl<'a> Game<'a> {
             zoom: 0.66,
         };
 
-        let physics_server = PhysicsServer::new();
+        let ship_camera = ShipCamera::new(bindings.camera_mode);
 
-        rl.hide_cursor();
+        let ghost_race = if time_attack {
+            Some(GhostRace::new(&sim))
+        } else {
+            None
+        };
+        let mut ghost_sprite = Sprite::new(player_tex.clone(), true, ship.sprite_scale);
+        ghost_sprite.set_tint(Color::WHITE.fade(0.35));
+        let mut ghost_object = GameObject::new();
+        ghost_object.sprite = Some(ghost_sprite);
+
+        let playback = if watch {
+            Some(Playback::new(&replay))
+        } else {
+            None
+        };
+        if !watch {
+            rl.hide_cursor();
+        }
 
         let mut arrow = GameObject::new();
         arrow.sprite = Some(Sprite::new(arrow_tex.clone(), true, 0.5));
@@ -220,40 +247,31 @@ impl<'a> Game<'a> {
             .load_render_texture(thread, window_width as u32 * 2, window_height as u32 * 2)
             .unwrap();
 
-        Game {
+        let mut game = Game {
             rl,
             thread,
             audio,
-            rng: Pcg64::seed_from_u64(seed),
-            fuel_mode,
+            sim,
+            ship,
+            bindings,
             draw_fps,
-            draw_collisions,
+            debug_draw: DebugDraw::new(),
             blur,
             bg_color,
-            physics_server,
-            rigid_body_set,
-            collider_set,
-            process_objects,
-            draw_objects,
-            phys_objects,
-            planet_objects,
-            gate_objects,
-            asteroid_colliders,
-            player_rc: None,
+            planet_objects: Vec::new(),
+            gate_objects: Vec::new(),
+            asteroid_objects: HashMap::new(),
+            player: None,
             player_tex,
             exhaust_tex,
-            player_score: 30,
-            time_since_start: 0.,
-            asteroid_spawn_timer: 0.,
-            completed: false,
+            step_accumulator: 0.,
             camera,
+            ship_camera,
             font,
             asteroid_tex,
             gate_tex,
             gate_off_tex,
             gate_darker_tex,
-            gate_count: 0,
-            next_gate: 0,
             arrow,
             arrow_tex,
             planet_shader,
@@ -267,48 +285,118 @@ impl<'a> Game<'a> {
             thruster_volume: 0.0,
             air_sound,
             air_volume: 0.0,
-        }
+            quicksave: None,
+            rewind: Rewind::default(),
+            replay,
+            playback,
+            ghost_race,
+            ghost_object,
+        };
+        game.rebuild_objects();
+        game
     }
 
-    pub fn remove_by_uuid(&mut self, uuid: &u128) {
-        self.process_objects.remove(uuid);
-        self.draw_objects.remove(uuid);
-        self.phys_objects.remove(uuid);
-        self.planet_objects.remove(uuid);
+    /// Creates drawables for everything in the simulation
+    fn rebuild_objects(&mut self) {
+        let sim = &self.sim;
+
+        self.planet_objects.clear();
+        for planet_state in sim.planets() {
+            let position = to_rv2(*sim.bodies()[planet_state.body].translation());
+            let (hue, sat, val) = planet_state.color_a;
+            let color_a = Color::color_from_hsv(hue, sat, val);
+            let (hue, sat, val) = planet_state.color_b;
+            let color_b = Color::color_from_hsv(hue, sat, val);
+            let mut planet = Planet::new(
+                position,
+                0.,
+                planet_state.radius,
+                color_a,
+                color_b,
+                self.asteroid_tex.clone(),
+            );
+            planet.set_body(planet_state.body);
+            self.planet_objects.push(planet);
+        }
+
+        self.gate_objects.clear();
+        for gate_state in sim.gates() {
+            let mut gate = Gate::new(
+                self.gate_tex.clone(),
+                self.gate_off_tex.clone(),
+                self.gate_darker_tex.clone(),
+            );
+            gate.gate_num = gate_state.gate_num;
+            gate.set_body(gate_state.body);
+            gate.update_state(&sim.bodies()[gate_state.body]);
+            self.gate_objects.push(gate);
+        }
+
+        // Asteroid drawables get created again by `sync_objects`
+        self.asteroid_objects.clear();
+
+        match (self.player.as_mut(), sim.ship()) {
+            // Keep the player (and its zoom) if there already is one
+            (Some(player), Some(ship)) => {
+                player.set_body(ship.body);
+                player.update_state(&sim.bodies()[ship.body]);
+            }
+            (None, Some(ship)) => {
+                let (x, y) = self.ship.exhaust_offset;
+                let mut player = Player::new(
+                    self.player_tex.clone(),
+                    self.exhaust_tex.clone(),
+                    self.ship.sprite_scale,
+                    vector![x, y],
+                );
+                player.set_body(ship.body);
+                player.update_state(&sim.bodies()[ship.body]);
+                self.player = Some(player);
+            }
+            (_, None) => self.player = None,
+        }
     }
 
-    pub fn remove_rigidbody(&mut self, rigid_body: RigidBodyHandle) {
-        self.rigid_body_set.remove(
-            rigid_body,
-            &mut self.physics_server.island_manager,
-            &mut self.collider_set,
-            &mut self.physics_server.joint_set,
-        );
+    /// Saves the simulation state in memory and to `QUICKSAVE_PATH`
+    fn quicksave(&mut self) {
+        let snapshot = self.sim.snapshot();
+        if let Err(err) = snapshot.save(QUICKSAVE_PATH) {
+            println!("Couldn't write quicksave: {}", err);
+        }
+        self.quicksave = Some((snapshot, self.rewind.cost()));
+        self.replay.quicksaved(&self.sim);
     }
 
-    pub fn remove_asteroid(&mut self, uuid: &u128, col: &ColliderHandle) {
-        let asteroid_body = *self.phys_objects.get(uuid).unwrap().borrow().get_body();
-        let scale = self.draw_objects.get(uuid).unwrap().borrow().get_scale();
-        let rigid_body = self.rigid_body_set.get(asteroid_body).unwrap();
-        let pos = *rigid_body.translation();
-        let vel = *rigid_body.linvel();
-        let dir = vel.normalize();
-        if scale > 0.3 {
-            // Spawn more smaller asteroids
-            for _ in 0..self.rng.gen_range(2..4) {
-                // Random velocities
-                let speed = vel.norm() * self.rng.gen_range(0.6..1.3);
-                let rot = Rotation::new(PI * self.rng.gen_range(0.5..1.5));
-                let linvel = (rot * dir) * speed;
-                let angvel = self.rng.gen_range(-10.0..10.0);
-                let new_scale = scale * self.rng.gen_range(0.3..0.8);
-
-                self.spawn_asteroid(pos - dir, new_scale, RigidBodyVelocity { linvel, angvel });
+    /// Restores the last quicksave (from memory, or from `QUICKSAVE_PATH`)
+    fn quickload(&mut self) {
+        if self.quicksave.is_none() {
+            match Snapshot::load(QUICKSAVE_PATH) {
+                Ok(snapshot) if snapshot.fits(&self.sim) => self.quicksave = Some((snapshot, 0)),
+                Ok(_) => {
+                    println!("Quicksave is from a different level or ship");
+                    return;
+                }
+                Err(err) => {
+                    println!("Couldn't read quicksave: {}", err);
+                    return;
+                }
             }
         }
-        self.remove_rigidbody(asteroid_body);
-        self.remove_by_uuid(uuid);
-        self.asteroid_colliders.remove(col);
+        if let Some((snapshot, cost)) = &self.quicksave {
+            self.sim.restore(snapshot);
+            // Loading doesn't undo what rewinding since the save cost
+            self.sim.take_points(self.rewind.cost() - cost);
+        }
+        self.rewind.clear();
+        self.replay.quickloaded(&self.sim);
+        self.step_accumulator = 0.;
+        self.rebuild_objects();
+        self.sync_objects();
+    }
+
+    /// Camera mode the player ended up with
+    pub fn camera_mode(&self) -> CameraMode {
+        self.ship_camera.mode
     }
 
     pub fn unload(&mut self) {
@@ -327,31 +415,102 @@ impl<'a> Game<'a> {
         }
     }
 
+    /// Reads ship controls from keyboard and gamepad
+    fn read_ship_input(&self) -> ShipInput {
+        let gamepad = GamepadState::read(self.rl);
+        self.bindings.ship_input(self.rl, gamepad.as_ref())
+    }
+
+    /// Turns the ship toward given point (for mouse steering)
+    fn steer_toward(&self, input: ShipInput, target: NVector2) -> ShipInput {
+        match self.sim.ship() {
+            Some(ship) => {
+                let body = &self.sim.bodies()[ship.body];
+                let direction = target - body.translation();
+                mouse::steer(input, body.rotation().angle(), body.angvel(), direction)
+            }
+            None => input,
+        }
+    }
+
+    /// Makes drawable objects match their bodies in the simulation
+    fn sync_objects(&mut self) {
+        let bodies = self.sim.bodies();
+        let colliders = self.sim.colliders();
+
+        if let (Some(player), Some(ship)) = (self.player.as_mut(), self.sim.ship()) {
+            player.update_state(&bodies[ship.body]);
+            player.thrusting = ship.is_thrusting();
+            player.boosting = ship.is_boosting();
+        }
+
+        for (col, body) in self.sim.asteroids() {
+            let asteroid = self.asteroid_objects.entry(*col).or_insert_with(|| {
+                let shape = colliders[*col].shape().as_convex_polygon();
+                let polygon = shape.map(|p| p.points().to_vec()).unwrap_or_default();
+                // Shade only has to look random
+                let shade = (col.into_raw_parts().0 % 8) as f32 / 8.0;
+                let mut asteroid = Asteroid::new(&polygon, shade);
+                asteroid.set_body(*body);
+                asteroid
+            });
+            asteroid.update_state(&bodies[*body]);
+        }
+
+        // Forget asteroids that were removed from the simulation
+        self.asteroid_objects
+            .retain(|col, _| colliders.get(*col).is_some());
+    }
+
     pub fn step(&mut self) -> Option<GameAction> {
         let delta = self.rl.get_frame_time();
 
+        // Rewind while the key is held (if the ruleset allows it)
+        if self.bindings.is_down(self.rl, Action::Rewind) && self.playback.is_none() {
+            if !self.paused && self.rewind.start(&mut self.sim) {
+                self.replay.rewind_started(&self.sim);
+            }
+        } else {
+            self.rewind.stop();
+        }
+
+        let input = if self.playback.is_some() {
+            self.replay.input(self.sim.steps()).unwrap_or_default()
+        } else if self.rewind.is_rewinding() {
+            ShipInput::default()
+        } else {
+            self.read_ship_input()
+        };
+        let completed = self.sim.is_completed();
+
         if !self.paused {
             // Thruster audio
             self.audio.update_music_stream(&mut self.thruster_sound);
             self.audio.update_music_stream(&mut self.thruster_sound2);
-            if self.rl.is_key_down(KeyboardKey::KEY_W) && !self.completed {
+            let boosting = self
+                .sim
+                .ship()
+                .map(|ship| ship.is_boosting())
+                .unwrap_or(false);
+            if input.forward > 0.0 && !completed {
                 self.thruster_volume *= 1.0 + (delta * 6.0);
             } else {
                 self.thruster_volume *= 1.0 - (delta * 4.0);
             }
-            self.thruster_volume = self.thruster_volume.clamp(0.1, 0.9);
+            // Afterburner is louder and higher
+            let max_volume = if boosting { 1.0 } else { 0.9 };
+            self.thruster_volume = self.thruster_volume.clamp(0.1, max_volume);
+            let pitch = if boosting { 1.3 } else { 1.0 };
             self.audio
                 .set_music_volume(&mut self.thruster_sound, self.thruster_volume);
             self.audio
                 .set_music_volume(&mut self.thruster_sound2, self.thruster_volume);
+            self.audio.set_music_pitch(&mut self.thruster_sound, pitch);
+            self.audio.set_music_pitch(&mut self.thruster_sound2, pitch);
 
             // Air release audio
             self.audio.update_music_stream(&mut self.air_sound);
-            if (self.rl.is_key_down(KeyboardKey::KEY_S)
-                || self.rl.is_key_down(KeyboardKey::KEY_A)
-                || self.rl.is_key_down(KeyboardKey::KEY_D))
-                && !self.completed
-            {
+            if (input.backward > 0.0 || input.left > 0.0 || input.right > 0.0) && !completed {
                 self.air_volume *= 1.0 + (delta * 6.0);
             } else {
                 self.air_volume *= 1.0 - (delta * 4.0);
@@ -359,12 +518,6 @@ impl<'a> Game<'a> {
             self.air_volume = self.air_volume.clamp(0.1, 0.9);
             self.audio
                 .set_music_volume(&mut self.air_sound, self.air_volume * 0.15);
-
-            // Tick timers
-            if !self.completed {
-                self.time_since_start += delta;
-            }
-            self.asteroid_spawn_timer += delta;
         }
 
         // Update camera center
@@ -384,200 +537,240 @@ impl<'a> Game<'a> {
             }
         }
 
-        let window_width = self.camera.offset.x * 2.0;
-        let window_height = self.camera.offset.y * 2.0;
-        let camera_diag =
-            ((window_width * window_width + window_height * window_height) as f32).sqrt();
-        let camera_diag_world = camera_diag / self.camera.zoom;
-        let view_r = camera_diag_world * 0.5;
-
-        // Spawning asteroids around the player
-        if self.asteroid_spawn_timer > 0.4 && self.asteroid_colliders.len() < 200 {
-            let r = view_r * (1.0 + self.rng.gen::<f32>());
-            let offset = Rotation::new(self.rng.gen::<f32>() * 2. * PI) * vector![0., 1.] * r;
-            let pos = to_nv2(self.camera.target) + offset;
-            let linvel = self.rng.gen_range(30.0..300.0)
-                * vector![
-                    self.rng.gen_range(-1.0..1.0f32),
-                    self.rng.gen_range(-1.0..1.0f32)
-                ]
-                .normalize();
-            let angvel = self.rng.gen_range(-10.0..10.0);
-            let scale = self.rng.gen_range(0.2..0.6);
-            self.spawn_asteroid(pos, scale, RigidBodyVelocity { linvel, angvel });
-            self.asteroid_spawn_timer = 0.;
+        // Always center mouse (unless it's used for steering or dragging the camera)
+        if !self.bindings.mouse_steering && self.playback.is_none() {
+            self.rl.set_mouse_position(self.camera.offset / 2.0);
         }
 
-        // Always center mouse
-        self.rl.set_mouse_position(self.camera.offset / 2.0);
-
         // Pause game
-        if self.rl.is_key_pressed(KeyboardKey::KEY_TAB) {
+        if self.bindings.is_pressed(self.rl, Action::Pause) {
             self.paused ^= true;
         }
 
         // Go back to menu
-        if self.rl.is_key_pressed(KeyboardKey::KEY_ESCAPE) {
+        if self.bindings.is_pressed(self.rl, Action::Menu) {
             return Some(GameAction::Menu);
         }
         // Restart game
-        if self.rl.is_key_pressed(KeyboardKey::KEY_R) {
+        if self.bindings.is_pressed(self.rl, Action::Restart) {
             return Some(GameAction::Restart);
         }
         // Restart game with new seed
-        if self.rl.is_key_pressed(KeyboardKey::KEY_N) {
+        if self.bindings.is_pressed(self.rl, Action::NewSeed) {
             return Some(GameAction::NewSeed);
         }
 
+        // Quick save and load (a replay can't be changed)
+        if self.playback.is_none() {
+            if self.bindings.is_pressed(self.rl, Action::QuickSave) {
+                self.quicksave();
+            }
+            if self.bindings.is_pressed(self.rl, Action::QuickLoad) {
+                self.quickload();
+            }
+        }
+
+        // Replay speed
+        if let Some(playback) = self.playback.as_mut() {
+            if self.bindings.is_pressed(self.rl, Action::ReplayFaster) {
+                playback.faster();
+            }
+            if self.bindings.is_pressed(self.rl, Action::ReplaySlower) {
+                playback.slower();
+            }
+        }
+
+        // Switch camera mode (and remember it), a dragged camera follows the ship again first
+        let free_camera = self
+            .playback
+            .as_mut()
+            .filter(|playback| playback.free_camera);
+        if self.bindings.is_pressed(self.rl, Action::CameraMode) {
+            if let Some(playback) = free_camera {
+                playback.free_camera = false;
+            } else {
+                self.ship_camera.mode = self.ship_camera.mode.next();
+                self.bindings.camera_mode = self.ship_camera.mode;
+                if let Err(err) = self.bindings.save() {
+                    println!("Couldn't save controls: {}", err);
+                }
+            }
+        }
+
+        // Race a ghost file dropped onto the window
+        if self.rl.is_file_dropped() {
+            let files = self.rl.get_dropped_files();
+            self.rl.clear_dropped_files();
+            if let Some(path) = files.first() {
+                let sim = &self.sim;
+                self.ghost_race
+                    .get_or_insert_with(|| GhostRace::new(sim))
+                    .load_rival(path, sim);
+            }
+        }
+
         // Toggle upscaling
-        if self.rl.is_key_pressed(KeyboardKey::KEY_B) {
+        if self.bindings.is_pressed(self.rl, Action::ToggleBlur) {
             self.blur ^= true;
         }
 
         // For debug
-        if self.rl.is_key_pressed(KeyboardKey::KEY_C) {
-            self.draw_collisions ^= true;
-        }
+        self.debug_draw.process(self.rl, &self.bindings);
 
         if !self.paused {
             // Processing
-            for object in self.process_objects.values() {
-                object.borrow_mut().process(&mut self.rl, delta);
-            }
+            if let Some(player) = self.player.as_mut() {
+                // Flight assists
+                if self.bindings.is_pressed(self.rl, Action::RotationDamping) {
+                    player.assists.rotation_damping ^= true;
+                }
+                if self.bindings.is_pressed(self.rl, Action::CycleOrientation) {
+                    player.assists.orientation = Orientation::cycle(player.assists.orientation);
+                }
+                if self.bindings.is_pressed(self.rl, Action::HoldVelocity) {
+                    player.assists.hold_velocity ^= true;
+                }
+                // Replays show the recorded ones
+                if self.playback.is_some() {
+                    player.assists = input.assists;
+                }
 
-            // Calculating gravity forces
-            let mut planets_vector: Vec<(NVector2, f32)> = Vec::new();
-            for planet in self.planet_objects.values() {
-                let pos = planet.borrow().get_position();
-                let mass = planet.borrow().get_mass();
-                planets_vector.push((vector![pos.x, pos.y], mass));
+                player.zoom_input = self.bindings.value(self.rl, Action::ZoomIn)
+                    - self.bindings.value(self.rl, Action::ZoomOut);
+                if self.bindings.mouse_steering {
+                    player.zoom_input += self.rl.get_mouse_wheel_move() * mouse::WHEEL_ZOOM;
+                }
+                player.process(&mut self.rl, delta);
             }
 
-            // Pre physics
-            for object in self.phys_objects.values_mut() {
-                let body = &mut self.rigid_body_set[*object.borrow().get_body()];
-                // Only calculate gravity for dynamic objects
-                if body.is_dynamic() {
-                    // Calculate gravity
-                    let mut gravity_force = vector![0., 0.];
-                    for planet_v in planets_vector.iter() {
-                        let dir = planet_v.0 - body.translation();
-                        let dist = dir.norm();
-                        if dist > 7777.7 {
-                            continue;
-                        }
-                        gravity_force +=
-                            dir.normalize() * G * planet_v.1 / dir.norm_squared().max(0.01);
+            // Simulation
+            // Render texture is twice the size of the window
+            let cursor = self
+                .rl
+                .get_screen_to_world2D(self.rl.get_mouse_position() * 2.0, self.camera);
+            // Asteroids come and go out of view (at any zoom)
+            let view_radius = self.camera.offset.length() / self.camera.zoom;
+            let speed = self.playback.as_ref().map(Playback::speed).unwrap_or(1.0);
+            self.step_accumulator += delta * speed;
+            let mut steps = 0;
+            while self.step_accumulator >= TIMESTEP {
+                if self.rewind.is_rewinding() {
+                    // Handles can point to different asteroids after going back
+                    if self.rewind.step_back(&mut self.sim) {
+                        self.asteroid_objects.clear();
+                        self.replay.went_back(&self.sim);
                     }
-                    // Apply gravity
-                    body.apply_force(gravity_force * body.mass(), true);
+                } else if self.playback.is_some() {
+                    self.replay.step(&mut self.sim);
+                } else {
+                    let mut input = if self.bindings.mouse_steering {
+                        self.steer_toward(input, to_nv2(cursor))
+                    } else {
+                        input
+                    };
+                    if let Some(player) = &self.player {
+                        input.assists = player.assists;
+                    }
+                    self.sim.set_view_radius(view_radius);
+                    self.replay.record(&self.sim, &input);
+                    self.sim.step(&input);
+                    self.rewind.record(&self.sim);
+                }
+                if let Some(ghost_race) = self.ghost_race.as_mut() {
+                    ghost_race.record(&self.sim);
+                }
+                self.step_accumulator -= TIMESTEP;
+                steps += 1;
+                // Don't try to catch up after a long hiccup
+                if steps >= MAX_STEPS_PER_FRAME {
+                    self.step_accumulator = 0.;
                 }
-                // Call objects physics process
-                object.borrow_mut().physics_process(delta, body);
             }
 
-            // Physics
-            self.physics_server
-                .step(&mut self.rigid_body_set, &mut self.collider_set);
-
-            let mut contact_events_guard = self
-                .physics_server
-                .event_handler
-                .contact_events
-                .lock()
-                .unwrap();
-
-            let mut contact_events = contact_events_guard.clone();
-            contact_events_guard.clear();
-            drop(contact_events_guard);
-
-            for event in contact_events.drain(..) {
-                if let ContactEvent::Started(col1, col2) = event {
-                    if let Some(pch) = self.physics_server.player_collider_handle {
-                        // One of them is the player
-                        if col1 == pch || col2 == pch {
-                            let bh1 = self.collider_set.get(col1).unwrap().parent().unwrap();
-                            let bh2 = self.collider_set.get(col2).unwrap().parent().unwrap();
-                            let b1 = self.rigid_body_set.get(bh1).unwrap();
-                            let b2 = self.rigid_body_set.get(bh2).unwrap();
-                            let vel_dif_mag = (b1.linvel() - b2.linvel()).norm();
-                            if vel_dif_mag > 150.0 {
-                                self.audio.play_sound_multi(&self.impact_sound);
-                            }
-                            if !self.completed {
-                                self.player_score -= 10;
-                            }
-                        }
-                        // None of them is the player
-                        else {
-                            // Try to get uuids of asteroids
-                            let asteroid1_uuid = self.asteroid_colliders.get(&col1).cloned();
-                            let asteroid2_uuid = self.asteroid_colliders.get(&col2).cloned();
-                            // Ignore collisions between asteroids
-                            if asteroid1_uuid.is_some() && asteroid2_uuid.is_some() {
-                                continue;
-                            }
-                            // Destroy asteroids
-                            if let Some(asteroid1_uuid) = asteroid1_uuid {
-                                self.remove_asteroid(&asteroid1_uuid, &col1);
-                            }
-                            if let Some(asteroid2_uuid) = asteroid2_uuid {
-                                self.remove_asteroid(&asteroid2_uuid, &col2);
-                            }
+            for event in self.sim.take_events() {
+                match event {
+                    SimEvent::ShipImpact(speed) => {
+                        if speed > 150.0 {
+                            self.audio.play_sound_multi(&self.impact_sound);
                         }
                     }
-                }
-            }
-
-            // When player goes through a gate
-            if !self.completed && self.physics_server.player_intersected {
-                // Get collider
-                if let Some(col_h) = self.physics_server.last_intersected {
-                    // Get body
-                    let body_h = &self.collider_set[col_h].parent();
-                    if let Some(body_h) = body_h {
-                        // Check if gate number is the one that player should go through
-                        let body = &self.rigid_body_set[*body_h];
-                        if body.user_data == self.next_gate.into() {
-                            // "Select" next gate
-                            self.next_gate += 1;
-                            self.player_score += 30;
+                    // Landing is a checkpoint (quick load goes back to it)
+                    SimEvent::Landed(_) if self.playback.is_none() => self.quicksave(),
+                    SimEvent::Landed(_) => {}
+                    SimEvent::ShieldHit => self.audio.play_sound_multi(&self.impact_sound),
+                    SimEvent::GatePassed(gate) => {
+                        if let Some(ghost_race) = self.ghost_race.as_mut() {
+                            ghost_race.gate_passed(gate);
                         }
                     }
                 }
             }
 
-            if self.player_score < 0 {
-                if let Some(player) = &self.player_rc {
-                    let mut player = player.borrow_mut();
-                    player.failed = true;
-                    self.completed = true;
-                }
+            if let Some(ghost_race) = self.ghost_race.as_mut() {
+                ghost_race.process(delta);
             }
 
-            self.completed |= self.next_gate >= self.gate_count;
-
-            // Update state of all physics objects
+            // Update state of all drawable objects
             // (This makes their position and rotation the same as their rigidbodies')
-            for object in self.phys_objects.values_mut() {
-                let body = &self.rigid_body_set[*object.borrow().get_body()];
-                object.borrow_mut().update_state(body);
-            }
+            self.sync_objects();
 
             // Camera
-            if let Some(player_rc) = &self.player_rc {
-                // Camera follows player
-                self.camera.target = to_rv2(lerp(
-                    to_nv2(self.camera.target),
-                    to_nv2(player_rc.borrow().get_position()),
-                    0.15,
-                ));
-                self.camera.rotation = -player_rc.borrow().get_rotation() * RAD2DEG as f32;
-                // Player controls zoom
-                self.camera.zoom = player_rc.borrow().get_zoom();
+            let free_camera = self
+                .playback
+                .as_ref()
+                .map(|playback| playback.free_camera)
+                .unwrap_or(false);
+            if let Some(player) = self.player.as_ref().filter(|_| !free_camera) {
+                let velocity = self
+                    .sim
+                    .ship()
+                    .map(|ship| *self.sim.bodies()[ship.body].linvel())
+                    .unwrap_or_else(NVector2::zeros);
+                let next_gate = self
+                    .sim
+                    .gates()
+                    .get(self.sim.next_gate() as usize)
+                    .map(|gate| *self.sim.bodies()[gate.body].translation());
+                let focus = CameraFocus {
+                    position: to_nv2(player.get_position()),
+                    velocity,
+                    rotation: player.get_rotation(),
+                    zoom: player.get_zoom(),
+                    next_gate,
+                    // Cursor steering needs a steady view
+                    steady: self.bindings.mouse_steering,
+                };
+                self.ship_camera.update(&mut self.camera, &focus, delta);
+            }
+        }
+
+        // Jumping around a replay on its timeline (works while paused too)
+        let seek = match self.playback.as_mut() {
+            Some(playback) => playback.scrub(self.rl, self.replay.len()),
+            None => None,
+        };
+        if let Some(step) = seek.filter(|step| *step != self.sim.steps()) {
+            if let Some(playback) = &self.playback {
+                playback.seek(&self.replay, &mut self.sim, step);
+            }
+            self.step_accumulator = 0.;
+            self.rebuild_objects();
+            self.sync_objects();
+            // A followed ship is in view right away
+            let free_camera = self.playback.as_ref().map(|playback| playback.free_camera);
+            if let (Some(player), Some(false)) = (&self.player, free_camera) {
+                self.camera.target = player.get_position();
             }
         }
 
+        // Dragging the camera around a replay (works while paused too)
+        if let Some(playback) = self.playback.as_mut() {
+            playback.move_camera(self.rl, &mut self.camera);
+        }
+
+        let next_gate = self.sim.next_gate();
+        let completed = self.sim.is_completed();
+
         let mut d = self.rl.begin_drawing(self.thread);
         d.clear_background(self.bg_color);
 
@@ -592,8 +785,7 @@ impl<'a> Game<'a> {
 
                 let color_a_loc = self.planet_shader.get_shader_location("colorA");
                 let color_b_loc = self.planet_shader.get_shader_location("colorB");
-                for planet in self.planet_objects.values_mut() {
-                    let planet = planet.borrow();
+                for planet in self.planet_objects.iter() {
                     let dist = (planet.get_position() - self.camera.target).length_sqr();
                     if dist > RENDER_DISTANCE {
                         continue;
@@ -608,30 +800,133 @@ impl<'a> Game<'a> {
 
                 let mut mode = mode1.begin_shader_mode(&self.def_shader);
 
+                // Fuel sources
+                for planet in self.sim.planets().iter().filter(|p| p.refuel) {
+                    let center = to_rv2(*self.sim.bodies()[planet.body].translation());
+                    mode.draw_ring(
+                        center,
+                        planet.radius * (1.0 + ORBIT_MIN_ALTITUDE),
+                        planet.radius * (1.0 + ORBIT_MAX_ALTITUDE),
+                        0.,
+                        360.,
+                        72,
+                        FUEL_COLOR.fade(0.08),
+                    );
+                }
+                for station in self.sim.stations() {
+                    let center = to_rv2(station.position);
+                    mode.draw_circle_v(center, STATION_DOCK_RADIUS, FUEL_COLOR.fade(0.15));
+                    mode.draw_ring(center, 30.0, 45.0, 0., 360., 24, Color::LIGHTGRAY);
+                    mode.draw_rectangle_v(
+                        center - rvec2(60.0, 6.0),
+                        rvec2(120.0, 12.0),
+                        Color::LIGHTGRAY,
+                    );
+                }
+                for pad in self.sim.landing_pads() {
+                    let (surface, normal) = self.sim.pad_position(pad);
+                    let color = if pad.visited {
+                        Color::LIGHTGRAY
+                    } else {
+                        Color::GOLD
+                    };
+                    let rotation = normal.y.atan2(normal.x) * RAD2DEG as f32 + 90.0;
+                    mode.draw_rectangle_pro(
+                        rrect(surface.x, surface.y, PAD_HALF_WIDTH * 2.0, 10.0),
+                        rvec2(PAD_HALF_WIDTH, 10.0),
+                        rotation,
+                        color,
+                    );
+                }
+                for canister in self.sim.canisters() {
+                    let center = to_rv2(canister.position);
+                    mode.draw_rectangle_v(
+                        center - rvec2(12.0, 20.0),
+                        rvec2(24.0, 40.0),
+                        FUEL_COLOR,
+                    );
+                    mode.draw_rectangle_v(
+                        center - rvec2(6.0, 26.0),
+                        rvec2(12.0, 6.0),
+                        Color::LIGHTGRAY,
+                    );
+                }
+
                 // Rendering objects
-                for object in self.draw_objects.values() {
-                    let object = object.borrow();
-                    let dist = (object.get_transform().position - self.camera.target).length_sqr();
-                    if dist > RENDER_DISTANCE {
-                        continue;
+                for (col, _) in self.sim.asteroids() {
+                    if let Some(asteroid) = self.asteroid_objects.get(col) {
+                        let dist = (asteroid.get_position() - self.camera.target).length_sqr();
+                        if dist > RENDER_DISTANCE {
+                            continue;
+                        }
+                        asteroid.draw(&mut mode);
                     }
+                }
+
+                // Tether (under the ship)
+                let tether = self.sim.tether().zip(self.sim.ship());
+                if let Some((tether, ship)) = tether {
+                    if let Some(anchor) = self.sim.tether_anchor(tether) {
+                        let ship_position = self.sim.bodies()[ship.body].translation();
+                        mode.draw_line_ex(
+                            to_rv2(anchor),
+                            to_rv2(*ship_position),
+                            4.0,
+                            Color::LIGHTGRAY,
+                        );
+                        mode.draw_circle_v(to_rv2(anchor), 8.0, Color::LIGHTGRAY);
+                    }
+                }
+
+                for shot in self.sim.shots() {
+                    let position = self.sim.bodies()[shot.body].translation();
+                    mode.draw_circle_v(to_rv2(*position), 6.0, Color::GOLD);
+                }
+
+                // Ghost (under the player)
+                let ghost_frame = self
+                    .ghost_race
+                    .as_ref()
+                    .and_then(|ghost_race| ghost_race.rival_frame(self.sim.time_since_start()));
+                if let Some(frame) = ghost_frame {
+                    let (x, y) = frame.position;
+                    self.ghost_object.set_position(rvec2(x, y));
+                    self.ghost_object.set_rotation(frame.rotation);
+                    self.ghost_object.draw(&mut mode);
+                }
+
+                if let Some(player) = &self.player {
+                    player.draw(&mut mode);
+                }
 
-                    object.draw(&mut mode);
+                // Shield bubble
+                if let Some(ship) = self.sim.ship().filter(|_| self.sim.shield_active()) {
+                    let position = to_rv2(*self.sim.bodies()[ship.body].translation());
+                    let radius =
+                        ship.stats.collider.half_height + ship.stats.collider.radius + 15.0;
+                    mode.draw_circle_v(position, radius, Color::SKYBLUE.fade(0.2));
+                    mode.draw_ring(
+                        position,
+                        radius - 3.0,
+                        radius,
+                        0.0,
+                        360.0,
+                        48,
+                        Color::SKYBLUE,
+                    );
                 }
 
                 // Render gates last
                 for gate in self.gate_objects.iter_mut() {
                     use std::cmp::Ordering;
 
-                    let mut gate = gate.borrow_mut();
-
                     let dist = (gate.get_position() - self.camera.target).length_sqr();
                     if dist > RENDER_DISTANCE {
                         continue;
                     }
 
                     // Gates already passed are off
-                    match gate.gate_num.cmp(&self.next_gate) {
+                    match gate.gate_num.cmp(&next_gate) {
                         Ordering::Less => gate.set_state(true, false),
                         Ordering::Equal => gate.set_state(false, true),
                         Ordering::Greater => gate.set_state(false, false),
@@ -641,16 +936,12 @@ impl<'a> Game<'a> {
                 }
 
                 // Draw arrow to next gate
-                if !self.completed {
-                    if let Some(player) = &self.player_rc {
-                        let player = player.borrow();
+                // (there's none after the last one, even if the level goes on)
+                let next_gate_object = self.gate_objects.get(next_gate as usize);
+                if !completed {
+                    if let (Some(player), Some(gate)) = (&self.player, next_gate_object) {
                         let pl_pos = player.get_position();
-                        let next_pos = self
-                            .gate_objects
-                            .get(self.next_gate as usize)
-                            .unwrap()
-                            .borrow()
-                            .get_position();
+                        let next_pos = gate.get_position();
                         let dir = pl_pos - next_pos;
                         if dir.length() > 256.0 {
                             let angle = dir.angle_to(rvec2(-1., 0.));
@@ -662,31 +953,8 @@ impl<'a> Game<'a> {
                     }
                 }
 
-                // Draw collisions
-                if self.draw_collisions {
-                    for object in self.phys_objects.values() {
-                        let body = &self.rigid_body_set[*object.borrow().get_body()];
-                        for collider in body.colliders() {
-                            let collider = &self.collider_set[*collider];
-                            let aabb = collider.shape().compute_local_aabb();
-                            let h_width = aabb.half_extents()[0];
-                            let h_height = aabb.half_extents()[1];
-                            let rec = rrect(
-                                collider.translation().x,
-                                collider.translation().y,
-                                h_width * 2.0,
-                                h_height * 2.0,
-                            );
-                            let origin = rvec2(h_width, h_height);
-                            mode.draw_rectangle_pro(
-                                rec,
-                                origin,
-                                RAD2DEG as f32 * body.rotation().angle(),
-                                COLL_COLOR,
-                            );
-                        }
-                    }
-                }
+                // Physics debug overlay
+                self.debug_draw.draw(&mut mode, &self.sim);
             }
         }
 
@@ -733,7 +1001,7 @@ impl<'a> Game<'a> {
             let mut line = -1.;
 
             // Player score
-            let score_text = format!("Score: {:}", self.player_score);
+            let score_text = format!("Score: {:}", self.sim.score());
             line += 1.0;
             d.draw_text_ex(
                 &self.font,
@@ -745,7 +1013,7 @@ impl<'a> Game<'a> {
             );
 
             // Gates
-            let gates_text = format!("Gates: {}/{}", self.next_gate, self.gate_count);
+            let gates_text = format!("Gates: {}/{}", next_gate, self.sim.gate_count());
             line += 1.0;
             d.draw_text_ex(
                 &self.font,
@@ -756,8 +1024,23 @@ impl<'a> Game<'a> {
                 Color::GREEN,
             );
 
+            // Asteroids left to shoot
+            if let Objective::ClearField(count) = self.sim.ruleset().objective {
+                let shot = self.sim.stats().asteroids_shot.min(count);
+                let asteroids_text = format!("Asteroids: {}/{}", shot, count);
+                line += 1.0;
+                d.draw_text_ex(
+                    &self.font,
+                    &asteroids_text,
+                    rvec2(0.0, 50.0 * line),
+                    50.0,
+                    0.0,
+                    Color::GREEN,
+                );
+            }
+
             // Time
-            let time_text = format!("Time: {:.2}", self.time_since_start);
+            let time_text = format!("Time: {:.2}", self.sim.time_since_start());
             line += 1.0;
             d.draw_text_ex(
                 &self.font,
@@ -768,12 +1051,74 @@ impl<'a> Game<'a> {
                 Color::GREEN,
             );
 
+            // Replay speed
+            if let Some(playback) = &self.playback {
+                let mut replay_text = if self.sim.steps() < self.replay.len() {
+                    format!("Replay {}x", playback.speed())
+                } else {
+                    "Replay ended".to_string()
+                };
+                if playback.free_camera {
+                    replay_text += ", free camera";
+                }
+                line += 1.0;
+                d.draw_text_ex(
+                    &self.font,
+                    &replay_text,
+                    rvec2(0.0, 50.0 * line),
+                    50.0,
+                    0.0,
+                    Color::LIGHTGRAY,
+                );
+            }
+
+            // Ghost's time and the last split
+            if let Some(ghost_race) = &self.ghost_race {
+                if let Some(best) = ghost_race.rival_time() {
+                    let best_text = format!("Ghost: {:.2}", best);
+                    line += 1.0;
+                    d.draw_text_ex(
+                        &self.font,
+                        &best_text,
+                        rvec2(0.0, 50.0 * line),
+                        50.0,
+                        0.0,
+                        Color::LIGHTGRAY,
+                    );
+                }
+                if let Some(split) = ghost_race.split() {
+                    let split_text = format!("Split: {:+.2}", split);
+                    let color = if split <= 0.0 {
+                        Color::GREEN
+                    } else {
+                        Color::RED
+                    };
+                    line += 1.0;
+                    d.draw_text_ex(
+                        &self.font,
+                        &split_text,
+                        rvec2(0.0, 50.0 * line),
+                        50.0,
+                        0.0,
+                        color,
+                    );
+                }
+            }
+
             // Fuel
-            if self.fuel_mode {
-                if let Some(player) = &self.player_rc {
-                    let mut player = player.borrow_mut();
-                    player.level_completed = self.completed;
-                    let fuel_text = format!("Fuel: {:.0}", player.fuel);
+            if self.sim.ruleset().fuel_mode {
+                if let Some(ship) = self.sim.ship() {
+                    let range = match self.sim.fuel_range() {
+                        Some(gates) => format!("~{:.1} gates", gates),
+                        None => format!("~{:.1}s of thrust", ship.fuel / ship.stats.burn_rate),
+                    };
+                    let refuelling = match self.sim.refuelling() {
+                        Some(Refuelling::Station) => ", docked",
+                        Some(Refuelling::Pad) => ", landed",
+                        Some(Refuelling::Orbit) => ", orbit refuel",
+                        None => "",
+                    };
+                    let fuel_text = format!("Fuel: {:.0} ({}{})", ship.fuel, range, refuelling);
                     line += 1.0;
                     d.draw_text_ex(
                         &self.font,
@@ -783,9 +1128,185 @@ impl<'a> Game<'a> {
                         0.0,
                         Color::GREEN,
                     );
+
+                    // Fuel used by each thruster
+                    let used = &self.sim.stats().fuel_used;
+                    let mut usage_text = format!(
+                        "Main {:.0}  Rev {:.0}  Side {:.0}",
+                        used.main,
+                        used.reverse,
+                        used.left + used.right
+                    );
+                    if self.sim.ruleset().limited_rcs {
+                        let rcs = ship.rcs / ship.stats.rcs_capacity * 100.0;
+                        usage_text += &format!("  RCS {:.0}%", rcs);
+                    }
+                    line += 1.0;
+                    d.draw_text_ex(
+                        &self.font,
+                        &usage_text,
+                        rvec2(0.0, 50.0 * line),
+                        40.0,
+                        0.0,
+                        Color::GREEN,
+                    );
                 }
             }
 
+            // Heat gauge
+            if let Some(ship) = self.sim.ship() {
+                line += 1.0;
+                let gauge_position = rvec2(0.0, 50.0 * line + 10.0);
+                let gauge_size = rvec2(240.0, 30.0);
+                let heat_color = if ship.is_overheated() {
+                    Color::RED
+                } else {
+                    Color::ORANGE.fade(0.5 + ship.heat * 0.5)
+                };
+                d.draw_rectangle_v(gauge_position, gauge_size, Color::DARKGRAY.fade(0.6));
+                d.draw_rectangle_v(
+                    gauge_position,
+                    rvec2(gauge_size.x * ship.heat, gauge_size.y),
+                    heat_color,
+                );
+                let position = self.sim.bodies()[ship.body].translation();
+                let heat_text = if ship.is_overheated() {
+                    format!("Overheated {:.1}", ship.overheat_timer)
+                } else if self.sim.is_shaded(*position) {
+                    "Heat (shade)".to_string()
+                } else {
+                    "Heat".to_string()
+                };
+                d.draw_text_ex(
+                    &self.font,
+                    &heat_text,
+                    gauge_position + rvec2(gauge_size.x + 10.0, -10.0),
+                    50.0,
+                    0.0,
+                    Color::GREEN,
+                );
+            }
+
+            // Rewinds
+            if let Some(rewinds_left) = self.rewind.rewinds_left(&self.sim) {
+                let rewind_text = format!("Rewinds: {}", rewinds_left);
+                line += 1.0;
+                d.draw_text_ex(
+                    &self.font,
+                    &rewind_text,
+                    rvec2(0.0, 50.0 * line),
+                    50.0,
+                    0.0,
+                    Color::GREEN,
+                );
+            }
+
+            // Flight assists (only the ones the ruleset allows)
+            if let Some(player) = &self.player {
+                let assists = player.assists.allowed(&self.sim.ruleset().assists);
+                let mut names = Vec::new();
+                if assists.rotation_damping {
+                    names.push("damping");
+                }
+                if let Some(orientation) = assists.orientation {
+                    names.push(orientation.name());
+                }
+                if assists.hold_velocity {
+                    names.push("hold");
+                }
+                if !names.is_empty() {
+                    let assists_text = format!("Assists: {}", names.join(", "));
+                    line += 1.0;
+                    d.draw_text_ex(
+                        &self.font,
+                        &assists_text,
+                        rvec2(0.0, 50.0 * line),
+                        50.0,
+                        0.0,
+                        Color::SKYBLUE,
+                    );
+                }
+            }
+
+            // Tether cooldown
+            if self.sim.tether_cooldown() > 0.0 {
+                let tether_text = format!("Tether: {:.1}", self.sim.tether_cooldown());
+                line += 1.0;
+                d.draw_text_ex(
+                    &self.font,
+                    &tether_text,
+                    rvec2(0.0, 50.0 * line),
+                    50.0,
+                    0.0,
+                    Color::GREEN,
+                );
+            }
+
+            // Shield
+            let shield_text = if self.sim.shield_active() {
+                "Shield: up".to_string()
+            } else if self.sim.shield_recharge() > 0.0 {
+                format!("Shield: {:.1}", self.sim.shield_recharge())
+            } else {
+                "Shield: ready".to_string()
+            };
+            line += 1.0;
+            d.draw_text_ex(
+                &self.font,
+                &shield_text,
+                rvec2(0.0, 50.0 * line),
+                50.0,
+                0.0,
+                Color::SKYBLUE,
+            );
+
+            // Replay timeline and the ship's values at this moment
+            if let Some(playback) = &self.playback {
+                let replay = &self.replay;
+                let step = self.sim.steps();
+                let input = step
+                    .checked_sub(1)
+                    .and_then(|step| replay.input(step))
+                    .unwrap_or_default();
+                playback.draw_timeline(&mut d, &self.font, step, replay.len());
+                playback.draw_inset(&mut d, &self.font, &self.sim, &input);
+            }
+
+            // Landed text
+            if self.sim.landed_on().is_some() && !completed {
+                let text = "Landed - thrust to take off";
+                let mut text_position = self.camera.offset / 2.0; // center
+                text_position += rvec2(-270.0, -290.0); // offset from center
+                d.draw_text_ex(&self.font, text, text_position, 50.0, 0.0, Color::GOLD);
+            }
+
+            // "Rewinding" text
+            if self.rewind.is_rewinding() {
+                let text = "<< Rewinding";
+                let mut text_position = self.camera.offset / 2.0; // center
+                text_position += rvec2(-120.0, -230.0); // offset from center
+                d.draw_text_ex(&self.font, text, text_position, 50.0, 0.0, Color::SKYBLUE);
+            }
+
+            // Debug overlay layers
+            if self.debug_draw.enabled {
+                let legend_position = rvec2(self.camera.offset.x - 260.0, 0.0); // top right
+                d.draw_text_ex(
+                    &self.font,
+                    &self.debug_draw.legend(&self.bindings),
+                    legend_position,
+                    30.0,
+                    0.0,
+                    Color::GREEN,
+                );
+            }
+
+            // Cursor
+            if self.bindings.mouse_steering {
+                let mouse = d.get_mouse_position();
+                d.draw_circle_lines(mouse.x as i32, mouse.y as i32, 12.0, Color::RAYWHITE);
+            }
+
             // "Paused" text
             if self.paused {
                 let text = "Paused";
@@ -795,27 +1316,36 @@ impl<'a> Game<'a> {
             }
 
             // Restart prompt
-            if self.completed {
-                let restart_text = if self
-                    .player_rc
-                    .as_deref()
-                    .map(|p| p.borrow().failed)
-                    .unwrap_or(true)
-                {
-                    "     Level failed\nPress R to restart"
+            if completed {
+                let restart_key = key_name(self.bindings.key(Action::Restart));
+                let restart_text = if self.sim.is_failed() {
+                    format!("     Level failed\nPress {} to restart", restart_key)
                 } else {
-                    "Level completed!\nPress R to restart"
+                    format!("Level completed!\nPress {} to restart", restart_key)
                 };
                 let mut text_position = self.camera.offset / 2.0; // center
                 text_position += rvec2(-150.0, -130.0); // offset from center
                 d.draw_text_ex(
                     &self.font,
-                    restart_text,
+                    &restart_text,
                     text_position,
                     50.0,
                     0.0,
                     Color::GOLD,
                 );
+                let mut stats_text = self.sim.stats().assists_summary();
+                if self.sim.ruleset().fuel_mode {
+                    stats_text += "\n";
+                    stats_text += &self.sim.stats().fuel_summary();
+                }
+                d.draw_text_ex(
+                    &self.font,
+                    &stats_text,
+                    text_position + rvec2(-150.0, 120.0),
+                    40.0,
+                    0.0,
+                    Color::GOLD,
+                );
             }
         }
 
@@ -827,278 +1357,34 @@ impl<'a> Game<'a> {
         while !self.rl.window_should_close() {
             let action = self.step();
             if let Some(action) = action {
+                self.save_replay();
                 return action;
             }
         }
+        self.save_replay();
         GameAction::Quit
     }
 
-    /// Spawns player
-    pub fn spawn_player(&mut self, position: NVector2, fuel: f32) {
-        assert!(self.player_rc.is_none(), "Can't spawn a second player");
-        let mut player = Player::new(self.player_tex.clone(), self.exhaust_tex.clone());
-        player.fuel = fuel;
-        player.fuel_mode = self.fuel_mode;
-
-        let rigid_body = RigidBodyBuilder::new_dynamic()
-            .translation(position)
-            .can_sleep(false)
-            .build();
-        let collider = ColliderBuilder::capsule_y(25.0, 14.0)
-            .position(Isometry::new(vector![0., -3.0], 0.0))
-            .active_events(ActiveEvents::INTERSECTION_EVENTS | ActiveEvents::CONTACT_EVENTS)
-            .build();
-
-        player.update_state(&rigid_body);
-
-        let player_body_handle = self.rigid_body_set.insert(rigid_body);
-        let player_col_handle = self.collider_set.insert_with_parent(
-            collider,
-            player_body_handle,
-            &mut self.rigid_body_set,
-        );
-        self.physics_server.player_collider_handle = Some(player_col_handle);
-
-        player.set_body(player_body_handle);
-
-        let uuid = player.get_uuid();
-        let player_rc = Rc::new(RefCell::new(player));
-        self.process_objects.insert(uuid, player_rc.clone());
-        self.draw_objects.insert(uuid, player_rc.clone());
-        self.phys_objects.insert(uuid, player_rc.clone());
-        self.player_rc = Some(player_rc);
-    }
-
-    /// Spawns an asteroid at given position
-    pub fn spawn_asteroid(
-        &mut self,
-        position: NVector2,
-        scale: f32,
-        velocities: RigidBodyVelocity,
-    ) {
-        let mut asteroid = GameObject::new();
-        asteroid.sprite = Some(Sprite::new(self.asteroid_tex.clone(), true, scale));
-
-        let rigid_body = RigidBodyBuilder::new_dynamic()
-            .translation(position)
-            .rotation(position.x * position.y)
-            .linvel(velocities.linvel)
-            .angvel(velocities.angvel)
-            .can_sleep(false)
-            .build();
-        let collider = ColliderBuilder::capsule_y(0.0, 40.0 * scale)
-            .restitution(0.8)
-            .density(2.0)
-            .active_events(ActiveEvents::CONTACT_EVENTS)
-            .build();
-
-        let uuid = asteroid.get_uuid();
-
-        asteroid.update_state(&rigid_body);
-
-        let rigid_body_handle = self.rigid_body_set.insert(rigid_body);
-
-        let col_handle = self.collider_set.insert_with_parent(
-            collider,
-            rigid_body_handle,
-            &mut self.rigid_body_set,
-        );
-        self.asteroid_colliders.insert(col_handle, uuid);
-        asteroid.set_body(rigid_body_handle);
-
-        let asteroid_rc = Rc::new(RefCell::new(asteroid));
-        self.process_objects.insert(uuid, asteroid_rc.clone());
-        self.draw_objects.insert(uuid, asteroid_rc.clone());
-        self.phys_objects.insert(uuid, asteroid_rc);
-    }
-
-    /// Spawns asteroids around given planet
-    pub fn spawn_asteroids_around_planet(&mut self, planet_pos: NVector2, planet_radius: f32) {
-        let asteroid_count = self.rng.gen_range(10..30);
-        for _ in 0..asteroid_count {
-            let rot = Rotation::new(self.rng.gen_range(0.0..2.0 * PI));
-            let offset = rot * vector![1., 0.] * self.rng.gen_range(1.5..5.5) * planet_radius;
-            let linvel = self.rng.gen_range(30.0..300.0)
-                * vector![
-                    self.rng.gen_range(-1.0..1.0f32),
-                    self.rng.gen_range(-1.0..1.0f32)
-                ]
-                .normalize();
-            let angvel = self.rng.gen_range(-10.0..10.0);
-            let scale = self.rng.gen_range(0.2..0.6);
-            self.spawn_asteroid(
-                planet_pos + offset,
-                scale,
-                RigidBodyVelocity { linvel, angvel },
-            );
+    /// Saves the run to `REPLAYS_DIR` (unless it was a replay itself or nothing happened)
+    fn save_replay(&mut self) {
+        if self.playback.is_some() || self.replay.is_empty() {
+            return;
         }
-    }
-
-    /// Spawns a planet at given position with given radius
-    pub fn spawn_planet(
-        &mut self,
-        position: NVector2,
-        radius: f32,
-        color_a: Color,
-        color_b: Color,
-    ) {
-        let mut planet = Planet::new(
-            to_rv2(position),
-            0.,
-            radius,
-            color_a,
-            color_b,
-            self.asteroid_tex.clone(),
+        self.replay.finish(&self.sim);
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_secs())
+            .unwrap_or(0);
+        let path = format!(
+            "{}/{}-{}-{}-{}.replay",
+            REPLAYS_DIR,
+            self.replay.seed,
+            self.replay.length,
+            self.replay.ruleset.name().to_lowercase(),
+            timestamp
         );
-
-        let rigid_body = RigidBodyBuilder::new_static()
-            .translation(position)
-            .can_sleep(false)
-            .build();
-        let collider = ColliderBuilder::ball(radius).density(8.0).build();
-
-        let rigid_body_handle = self.rigid_body_set.insert(rigid_body);
-        self.collider_set
-            .insert_with_parent(collider, rigid_body_handle, &mut self.rigid_body_set);
-        planet.set_body(rigid_body_handle);
-
-        let uuid = planet.get_uuid();
-        let planet_rc = Rc::new(RefCell::new(planet));
-        self.phys_objects.insert(uuid, planet_rc.clone());
-        self.planet_objects.insert(uuid, planet_rc);
-    }
-
-    /// Spawns a gate at given position
-    pub fn spawn_gate(&mut self, position: NVector2, rotation: f32) {
-        let mut gate = Gate::new(
-            self.gate_tex.clone(),
-            self.gate_off_tex.clone(),
-            self.gate_darker_tex.clone(),
-        );
-        gate.gate_num = self.gate_count;
-
-        let width = 15.0;
-        let height = 115.0;
-
-        let rigid_body = RigidBodyBuilder::new_static()
-            .translation(position)
-            .can_sleep(false)
-            .user_data(self.gate_count.into())
-            .rotation(rotation)
-            .build();
-
-        let area_collider = ColliderBuilder::cuboid(width * 0.3, height)
-            .sensor(true)
-            .build();
-        let gate_collider_1 = ColliderBuilder::ball(width)
-            .translation(vector![0., height])
-            .build();
-        let gate_collider_2 = ColliderBuilder::ball(width)
-            .translation(vector![0., -(height)])
-            .build();
-
-        let rigid_body_handle = self.rigid_body_set.insert(rigid_body);
-        self.collider_set.insert_with_parent(
-            area_collider,
-            rigid_body_handle,
-            &mut self.rigid_body_set,
-        );
-
-        self.collider_set.insert_with_parent(
-            gate_collider_1,
-            rigid_body_handle,
-            &mut self.rigid_body_set,
-        );
-        self.collider_set.insert_with_parent(
-            gate_collider_2,
-            rigid_body_handle,
-            &mut self.rigid_body_set,
-        );
-
-        gate.set_body(rigid_body_handle);
-
-        let uuid = gate.get_uuid();
-        let gate_rc = Rc::new(RefCell::new(gate));
-        self.phys_objects.insert(uuid, gate_rc.clone());
-        self.gate_objects.push(gate_rc);
-
-        self.gate_count += 1;
-    }
-
-    /// Spawns a planet with gates around it
-    pub fn spawn_planet_with_gates(&mut self, position: NVector2, radius: f32, gate_count: u16) {
-        assert!(gate_count < 6, "Gate count must be less than 6");
-
-        let hue = self.rng.gen::<f32>() * 250.;
-        let sat = self.rng.gen::<f32>() * 0.3 + 0.3;
-        let color_a = Color::color_from_hsv(hue, sat, 0.9);
-        let hue = hue + 135.;
-        let sat = self.rng.gen::<f32>() * 0.3 + 0.5;
-        let color_b = Color::color_from_hsv(hue, sat, 0.5);
-
-        self.spawn_planet(position, radius, color_a, color_b);
-
-        let direction = (self.rng.gen::<f32>() - 0.5).signum();
-
-        let start_angle = self.rng.gen::<f32>() * PI;
-
-        let angle_step = 2.0 * PI / (5.0 + self.rng.gen::<f32>() * 2.0);
-        for i in 0..gate_count {
-            let gate_offset: f32 = radius * (self.rng.gen::<f32>() + 1.2) + 100.;
-            let rot = Rotation::new(start_angle + angle_step * direction * i as f32);
-            let offset = rot.into_inner() * gate_offset;
-            let pos = vector![offset.re, offset.im] + position;
-            self.spawn_gate(pos, rot.angle() + PI / 2.0);
-        }
-    }
-
-    /// Spawns many planets at random positions with gates around them
-    pub fn spawn_many_planets_with_gates(&mut self, num_gates: u16) {
-        let mut planets: Vec<(NVector2, f32)> = Vec::new();
-
-        let radius_range = 300.0..700.0;
-
-        let mut gates_left = num_gates;
-
-        let mut last_position: NVector2 = vector![0., 0.];
-        let mut last_radius = 0.;
-        while gates_left > 0 {
-            let mut position_valid = false;
-            let radius = self.rng.gen_range(radius_range.clone());
-            let mut distance = (last_radius + radius) * (3.0 + self.rng.gen::<f32>());
-            let mut pos: NVector2 = vector![0., 0.];
-            while !position_valid {
-                let angle = self.rng.gen::<f32>() * PI * 2.0;
-                let rot = Rotation::new(angle);
-                let offset = rot.into_inner() * distance;
-                distance *= 1.05;
-                pos = vector![offset.re, offset.im] + last_position;
-
-                // Check if planet too close to other planets
-                position_valid = true;
-                for planet in planets.iter() {
-                    let dist = (pos - planet.0).norm();
-                    let min_dist = (radius + planet.1) * 2.8;
-                    if dist < min_dist {
-                        position_valid = false;
-                        break;
-                    }
-                }
-            }
-
-            let mut gate_count =
-                ((self.rng.gen_range(1..6) + self.rng.gen_range(0..6)) as f32 * 0.5).ceil() as u16;
-            if self.rng.gen_bool(0.3) {
-                gate_count = 0;
-            } else {
-                last_radius = radius;
-                last_position = pos;
-            }
-            gate_count = gate_count.min(gates_left);
-            gates_left -= gate_count;
-            self.spawn_planet_with_gates(pos, radius, gate_count);
-            self.spawn_asteroids_around_planet(pos, radius);
-            planets.push((pos, radius));
+        if let Err(err) = self.replay.save(&path) {
+            println!("Couldn't save replay: {}", err);
         }
     }
 }
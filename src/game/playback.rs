@@ -0,0 +1,230 @@
+//! Watching a replay: speed control, a timeline to jump around on,
+//! a camera that can be dragged around and an inset with the ship's values
+
+use crate::simulation::{Marker, Replay, ShipInput, Simulation, Timeline, TIMESTEP};
+
+use raylib::prelude::*;
+
+/// Playback speeds to choose from
+const SPEEDS: [f32; 5] = [0.25, 0.5, 1.0, 2.0, 4.0];
+/// Space between the timeline and the sides of the window
+const TIMELINE_MARGIN: f32 = 40.0;
+const TIMELINE_HEIGHT: f32 = 30.0;
+/// Size of the inset with the ship's values
+const INSET_SIZE: Vector2 = Vector2 { x: 400.0, y: 250.0 };
+
+pub struct Playback {
+    /// Index into `SPEEDS`
+    speed: usize,
+    /// Camera was dragged away from the ship
+    pub free_camera: bool,
+    /// Where the mouse was last frame while dragging (in screen space)
+    drag_from: Option<Vector2>,
+    timeline: Timeline,
+    /// Mouse button went down on the timeline and is still held
+    scrubbing: bool,
+}
+
+impl Playback {
+    /// Simulates the whole replay once to find keyframes and markers
+    pub fn new(replay: &Replay) -> Self {
+        Playback {
+            speed: 2,
+            free_camera: false,
+            drag_from: None,
+            timeline: Timeline::new(replay),
+            scrubbing: false,
+        }
+    }
+
+    /// Simulated seconds per real second
+    pub fn speed(&self) -> f32 {
+        SPEEDS[self.speed]
+    }
+
+    pub fn faster(&mut self) {
+        self.speed = (self.speed + 1).min(SPEEDS.len() - 1);
+    }
+
+    pub fn slower(&mut self) {
+        self.speed = self.speed.saturating_sub(1);
+    }
+
+    /// Step to jump to while the timeline is clicked or dragged along
+    pub fn scrub(&mut self, rl: &RaylibHandle, length: u32) -> Option<u32> {
+        let bounds = timeline_bounds(rl);
+        let mouse = rl.get_mouse_position();
+        if rl.is_mouse_button_pressed(MouseButton::MOUSE_LEFT_BUTTON) {
+            self.scrubbing = bounds.check_collision_point_rec(mouse);
+        }
+        if !rl.is_mouse_button_down(MouseButton::MOUSE_LEFT_BUTTON) {
+            self.scrubbing = false;
+        }
+        if !self.scrubbing {
+            return None;
+        }
+        let share = ((mouse.x - bounds.x) / bounds.width).clamp(0.0, 1.0);
+        Some((share * length as f32).round() as u32)
+    }
+
+    /// Puts `sim` at `step` of the replay
+    pub fn seek(&self, replay: &Replay, sim: &mut Simulation, step: u32) {
+        self.timeline.seek(replay, sim, step);
+    }
+
+    /// Moves the camera while the left mouse button is held, the wheel zooms
+    pub fn move_camera(&mut self, rl: &RaylibHandle, camera: &mut Camera2D) {
+        // Render texture is twice the size of the window
+        let mouse = rl.get_mouse_position() * 2.0;
+        if rl.is_mouse_button_down(MouseButton::MOUSE_LEFT_BUTTON) && !self.scrubbing {
+            if let Some(from) = self.drag_from {
+                let moved = rl.get_screen_to_world2D(from, *camera)
+                    - rl.get_screen_to_world2D(mouse, *camera);
+                camera.target += moved;
+                self.free_camera = true;
+            }
+            self.drag_from = Some(mouse);
+        } else {
+            self.drag_from = None;
+        }
+        if self.free_camera {
+            let wheel = rl.get_mouse_wheel_move();
+            camera.zoom = (camera.zoom * (1.0 + wheel * 0.1)).clamp(0.05, 4.0);
+        }
+    }
+
+    /// Timeline with gates passed (green) and collisions (red) on it
+    pub fn draw_timeline(&self, d: &mut RaylibDrawHandle, font: &Font, step: u32, length: u32) {
+        let bounds = timeline_bounds(d);
+        let x_at = |step: u32| bounds.x + bounds.width * step as f32 / length.max(1) as f32;
+        let (top, bottom) = (bounds.y, bounds.y + bounds.height);
+
+        d.draw_rectangle_rec(bounds, Color::DARKGRAY.fade(0.6));
+        let played = rrect(
+            bounds.x,
+            top,
+            x_at(step.min(length)) - bounds.x,
+            bounds.height,
+        );
+        d.draw_rectangle_rec(played, Color::SKYBLUE.fade(0.4));
+
+        for (at, marker) in self.timeline.markers() {
+            let x = x_at(*at);
+            match marker {
+                Marker::GatePassed(gate) => {
+                    d.draw_line_ex(rvec2(x, top - 8.0), rvec2(x, bottom), 3.0, Color::GREEN);
+                    d.draw_text_ex(
+                        font,
+                        &(gate + 1).to_string(),
+                        rvec2(x - 6.0, top - 38.0),
+                        30.0,
+                        0.0,
+                        Color::GREEN,
+                    );
+                }
+                Marker::Collision => {
+                    let middle = top + bounds.height / 2.0;
+                    d.draw_line_ex(rvec2(x, middle), rvec2(x, bottom), 2.0, Color::RED);
+                }
+            }
+        }
+
+        // Where playback is
+        let x = x_at(step.min(length));
+        d.draw_line_ex(
+            rvec2(x, top - 4.0),
+            rvec2(x, bottom + 4.0),
+            4.0,
+            Color::RAYWHITE,
+        );
+        let time_text = format!(
+            "{:.1} / {:.1}",
+            step as f32 * TIMESTEP,
+            length as f32 * TIMESTEP
+        );
+        d.draw_text_ex(
+            font,
+            &time_text,
+            rvec2(bounds.x, bottom + 2.0),
+            30.0,
+            0.0,
+            Color::LIGHTGRAY,
+        );
+    }
+
+    /// The ship's values and the controls held at this moment of the replay
+    /// (in the corner above the timeline, so they're there while the camera is free)
+    pub fn draw_inset(
+        &self,
+        d: &mut RaylibDrawHandle,
+        font: &Font,
+        sim: &Simulation,
+        input: &ShipInput,
+    ) {
+        let ship = match sim.ship() {
+            Some(ship) => ship,
+            None => return,
+        };
+        let bounds = timeline_bounds(d);
+        let corner = rvec2(
+            bounds.x + bounds.width - INSET_SIZE.x,
+            bounds.y - 50.0 - INSET_SIZE.y,
+        );
+        d.draw_rectangle_v(corner, INSET_SIZE, Color::BLACK.fade(0.6));
+        d.draw_rectangle_lines_ex(
+            rrect(corner.x, corner.y, INSET_SIZE.x, INSET_SIZE.y),
+            2,
+            Color::LIGHTGRAY,
+        );
+
+        let body = &sim.bodies()[ship.body];
+        let heading = body.rotation().angle().to_degrees().rem_euclid(360.0);
+        let mut lines = vec![
+            format!("Speed: {:.0}", body.linvel().norm()),
+            format!("Heading: {:.0}", heading),
+            format!("Heat: {:.0}%", ship.heat * 100.0),
+        ];
+        if sim.ruleset().fuel_mode {
+            lines.push(format!("Fuel: {:.0}", ship.fuel));
+        }
+        let text_position = corner + rvec2(15.0, 10.0);
+        d.draw_text_ex(
+            font,
+            &lines.join("\n"),
+            text_position,
+            30.0,
+            0.0,
+            Color::GREEN,
+        );
+
+        // Controls held (lit) and not held (dim)
+        let controls = [
+            ("Thrust", input.forward > 0.0),
+            ("Rev", input.backward > 0.0),
+            ("Side", input.left > 0.0 || input.right > 0.0),
+            ("Turn", input.rotate_left > 0.0 || input.rotate_right > 0.0),
+            ("Boost", input.boost),
+            ("Tether", input.tether),
+            ("Fire", input.fire),
+            ("Shield", input.shield),
+        ];
+        for (i, (name, held)) in controls.iter().enumerate() {
+            let position =
+                corner + rvec2(15.0 + 95.0 * (i % 4) as f32, 170.0 + 35.0 * (i / 4) as f32);
+            let color = if *held { Color::GOLD } else { Color::DARKGRAY };
+            d.draw_text_ex(font, name, position, 30.0, 0.0, color);
+        }
+    }
+}
+
+/// Timeline along the bottom of the window
+fn timeline_bounds(rl: &RaylibHandle) -> Rectangle {
+    let width = rl.get_screen_width() as f32;
+    let height = rl.get_screen_height() as f32;
+    rrect(
+        TIMELINE_MARGIN,
+        height - 2.0 * TIMELINE_HEIGHT - 20.0,
+        width - 2.0 * TIMELINE_MARGIN,
+        TIMELINE_HEIGHT,
+    )
+}
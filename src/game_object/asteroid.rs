@@ -0,0 +1,85 @@
+use super::{Drawable, GameObject, PhysicsObject, Spatial};
+
+use crate::{impl_spatial, DrawHandle};
+
+use raylib::prelude::*;
+
+use crate::math::*;
+
+use rapier2d::prelude::*;
+
+pub struct Asteroid {
+    game_object: GameObject,
+    /// Outline in local space, in the order raylib draws triangles
+    points: Vec<Vector2>,
+    fill_color: Color,
+    edge_color: Color,
+}
+
+impl Asteroid {
+    /// `polygon` is the collider's convex polygon (counter-clockwise)
+    pub fn new(polygon: &[NPoint2], shade: f32) -> Self {
+        // Raylib wants the triangle fan in the opposite order, as Y axis points down
+        let points = polygon.iter().rev().map(|p| to_rv2(p.coords)).collect();
+
+        let fill_color = Color::color_from_hsv(25.0, 0.25, 0.35 + shade * 0.2);
+        let edge_color = Color::color_from_hsv(25.0, 0.3, 0.2 + shade * 0.1);
+
+        Asteroid {
+            game_object: GameObject::new(),
+            points,
+            fill_color,
+            edge_color,
+        }
+    }
+
+    fn world_points(&self) -> Vec<Vector2> {
+        let transform = self.game_object.get_transform();
+        self.points
+            .iter()
+            .map(|p| p.rotated(transform.rotation) + transform.position)
+            .collect()
+    }
+}
+
+impl_spatial!(Asteroid);
+
+impl Drawable for Asteroid {
+    fn draw(&self, rl: &mut DrawHandle!()) {
+        let points = self.world_points();
+        rl.draw_triangle_fan(&points, self.fill_color);
+        for (i, a) in points.iter().enumerate() {
+            let b = points[(i + 1) % points.len()];
+            rl.draw_line_ex(*a, b, 4.0, self.edge_color);
+        }
+    }
+
+    fn get_scale(&self) -> f32 {
+        1.0
+    }
+
+    #[allow(unused_variables)]
+    fn set_scale(&mut self, scale: f32) {}
+
+    fn set_tint(&mut self, tint: Color) {
+        self.fill_color = tint;
+    }
+
+    fn get_transform(&self) -> Transform2D {
+        self.game_object.get_transform()
+    }
+}
+
+impl PhysicsObject for Asteroid {
+    fn get_body(&self) -> &RigidBodyHandle {
+        self.game_object.get_body()
+    }
+
+    fn set_body(&mut self, body: RigidBodyHandle) {
+        self.game_object.set_body(body);
+    }
+
+    fn update_state(&mut self, body: &RigidBody) {
+        self.game_object.update_state(body);
+    }
+}
@@ -32,10 +32,6 @@ impl Gate {
         }
     }
 
-    pub fn get_uuid(&self) -> u128 {
-        self.game_object.get_uuid()
-    }
-
     pub fn set_state(&mut self, is_off: bool, highlight: bool) {
         if is_off == self.is_off && highlight == self.highlight {
             return;
@@ -67,10 +63,6 @@ impl PhysicsObject for Gate {
         self.game_object.set_body(body);
     }
 
-    fn physics_process(&mut self, delta: f32, body: &mut RigidBody) {
-        self.game_object.physics_process(delta, body);
-    }
-
     fn update_state(&mut self, body: &RigidBody) {
         self.game_object.update_state(body);
     }
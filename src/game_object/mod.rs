@@ -19,6 +19,9 @@ pub use planet::Planet;
 mod gate;
 pub use gate::Gate;
 
+mod asteroid;
+pub use asteroid::Asteroid;
+
 use rapier2d::prelude::*;
 
 use rand::prelude::*;
@@ -77,7 +80,6 @@ pub trait Processing {
 pub trait PhysicsObject {
     fn get_body(&self) -> &RigidBodyHandle;
     fn set_body(&mut self, body: RigidBodyHandle);
-    fn physics_process(&mut self, delta: f32, body: &mut RigidBody);
     fn update_state(&mut self, body: &RigidBody);
 }
 
@@ -158,7 +160,4 @@ impl PhysicsObject for GameObject {
         self.set_position(pos);
         self.transform.rotation = rot;
     }
-
-    #[allow(unused_variables)]
-    fn physics_process(&mut self, delta: f32, body: &mut RigidBody) {}
 }
@@ -7,16 +7,11 @@ use crate::math::Transform2D;
 
 use rapier2d::prelude::*;
 
-use rand::prelude::*;
-use rand_pcg::Pcg64;
-
 pub struct Planet {
     transform: Transform2D,
     physics_body: Option<RigidBodyHandle>,
-    mass: f32,
     pub color_a: Color,
     pub color_b: Color,
-    uuid: u128,
     sprite: Sprite,
 }
 
@@ -36,21 +31,11 @@ impl Planet {
         Planet {
             transform,
             physics_body: None,
-            mass: 0.,
             color_a,
             color_b,
-            uuid: Pcg64::from_entropy().gen(),
             sprite,
         }
     }
-
-    pub fn get_mass(&self) -> f32 {
-        self.mass
-    }
-
-    pub fn get_uuid(&self) -> u128 {
-        self.uuid
-    }
 }
 
 impl Spatial for Planet {
@@ -101,16 +86,12 @@ impl PhysicsObject for Planet {
         self.physics_body = Some(body);
     }
 
-    #[allow(unused_variables)]
-    fn physics_process(&mut self, delta: f32, body: &mut RigidBody) {}
-
     fn update_state(&mut self, body: &RigidBody) {
         let pos = Vector2 {
             x: body.translation().x,
             y: body.translation().y,
         };
         let rot = body.rotation().angle();
-        self.mass = body.mass();
         self.set_position(pos);
         self.transform.rotation = rot;
     }
@@ -5,44 +5,50 @@ use crate::{impl_spatial, DrawHandle};
 use raylib::prelude::*;
 
 use crate::math::*;
+use crate::simulation::Assists;
 
 use rapier2d::prelude::*;
 
 pub struct Player {
     game_object: GameObject,
     exhaust_sprite: Sprite,
+    /// Bigger and hotter exhaust of the afterburner
+    boost_sprite: Sprite,
     exhaust_offset: NVector2,
-    pub lin_speed: f32,
-    pub ang_speed: f32,
-    move_vec: NVector2, // add this to lin vel on next phys process
-    rot: f32,           // add this to ang vel on next phys process
+    /// Main engine is on (draws exhaust)
+    pub thrusting: bool,
+    pub boosting: bool,
+    /// Positive zooms in, negative zooms out (1.0 is the normal speed)
+    pub zoom_input: f32,
     zoom: f32,
-    pub fuel: f32,
-    pub fuel_mode: bool,
-    pub level_completed: bool,
-    pub failed: bool,
+    /// Flight assists switched on (sent to the simulation with every input)
+    pub assists: Assists,
 }
 
 #[allow(dead_code)]
 impl Player {
-    pub fn new(main_texture: WeakTexture2D, exhaust_texture: WeakTexture2D) -> Self {
+    pub fn new(
+        main_texture: WeakTexture2D,
+        exhaust_texture: WeakTexture2D,
+        scale: f32,
+        exhaust_offset: NVector2,
+    ) -> Self {
         let mut game_object = GameObject::new();
-        game_object.sprite = Some(Sprite::new(main_texture, true, 0.35));
-        let exhaust_sprite = Sprite::new(exhaust_texture, true, 0.475);
+        game_object.sprite = Some(Sprite::new(main_texture, true, scale));
+        let exhaust_sprite = Sprite::new(exhaust_texture.clone(), true, 0.475);
+        let mut boost_sprite = Sprite::new(exhaust_texture, true, 0.7);
+        boost_sprite.set_tint(Color::new(140, 200, 255, 255));
 
         Player {
             game_object,
             exhaust_sprite,
-            exhaust_offset: vector![0., 50.],
-            lin_speed: 70.0,
-            ang_speed: 1.45,
-            move_vec: NVector2::zeros(),
-            rot: 0.0,
+            boost_sprite,
+            exhaust_offset,
+            thrusting: false,
+            boosting: false,
+            zoom_input: 0.0,
             zoom: 0.6,
-            fuel: 10.,
-            fuel_mode: false,
-            level_completed: false,
-            failed: false,
+            assists: Assists::default(),
         }
     }
     pub fn get_zoom(&self) -> f32 {
@@ -57,12 +63,19 @@ impl_spatial!(Player);
 
 impl Drawable for Player {
     fn draw(&self, rl: &mut DrawHandle!()) {
-        if self.move_vec.y < 0. {
+        if self.thrusting {
             let mut exhaust_transform = self.game_object.transform;
             let rot = Rotation::new(exhaust_transform.rotation);
-            let offset = to_rv2(rot * self.exhaust_offset);
-            exhaust_transform.position += offset;
-            self.exhaust_sprite.draw(rl, &exhaust_transform);
+            if self.boosting {
+                // Longer flame reaches further back
+                let offset = to_rv2(rot * (self.exhaust_offset * 1.4));
+                exhaust_transform.position += offset;
+                self.boost_sprite.draw(rl, &exhaust_transform);
+            } else {
+                let offset = to_rv2(rot * self.exhaust_offset);
+                exhaust_transform.position += offset;
+                self.exhaust_sprite.draw(rl, &exhaust_transform);
+            }
         }
         self.game_object.draw(rl);
     }
@@ -85,62 +98,14 @@ impl Drawable for Player {
 }
 
 impl Processing for Player {
+    #[allow(unused_variables)]
     fn process(&mut self, rl: &mut RaylibHandle, delta: f32) {
-        // Movement
-        let move_u = rl.is_key_down(KeyboardKey::KEY_W);
-        let move_d = rl.is_key_down(KeyboardKey::KEY_S);
-        let move_l = rl.is_key_down(KeyboardKey::KEY_A);
-        let move_r = rl.is_key_down(KeyboardKey::KEY_D);
-
-        self.move_vec = vector![0., 0.];
-
-        if self.fuel > 0. && !self.failed {
-            let mut moves_count = 0;
-            if move_u {
-                self.move_vec.y -= self.lin_speed * 3.0;
-                moves_count += 1;
-            }
-            if move_d {
-                self.move_vec.y += self.lin_speed;
-                moves_count += 1;
-            }
-            if move_l {
-                self.move_vec.x -= self.lin_speed;
-                moves_count += 1;
-            }
-            if move_r {
-                self.move_vec.x += self.lin_speed;
-                moves_count += 1;
-            }
-            if !self.level_completed && self.fuel_mode {
-                self.fuel -= delta * 10. * moves_count as f32;
-            }
-        }
-        self.fuel = self.fuel.max(0.);
-
-        // Rotating
-        let rot_l = rl.is_key_down(KeyboardKey::KEY_I);
-        let rot_r = rl.is_key_down(KeyboardKey::KEY_O);
-
-        self.rot = 0.0;
-        if self.fuel > 0. && !self.failed {
-            if rot_l {
-                self.rot -= self.ang_speed;
-            }
-            if rot_r {
-                self.rot += self.ang_speed;
-            }
-        }
-
         // Zoom
-        let zoom_plus = rl.is_key_down(KeyboardKey::KEY_K);
-        let zoom_minus = rl.is_key_down(KeyboardKey::KEY_L);
-
-        if zoom_plus {
-            self.zoom *= 1.0 / (1.0 + delta * 4.0);
+        if self.zoom_input > 0.0 {
+            self.zoom *= 1.0 / (1.0 + delta * 4.0 * self.zoom_input);
         }
-        if zoom_minus {
-            self.zoom *= 1.0 + delta * 4.0;
+        if self.zoom_input < 0.0 {
+            self.zoom *= 1.0 - delta * 4.0 * self.zoom_input;
         }
         self.zoom = self.zoom.min(4.0).max(0.25);
     }
@@ -155,18 +120,6 @@ impl PhysicsObject for Player {
         self.game_object.set_body(body);
     }
 
-    fn physics_process(&mut self, delta: f32, body: &mut RigidBody) {
-        let rot = Rotation::new(self.get_rotation());
-        let move_vec = rot * self.move_vec;
-        body.set_linvel(body.linvel() + move_vec * delta, true);
-        let mut angvel = body.angvel() + self.rot * delta;
-        if angvel > 3.0 {
-            angvel /= 1.0 + delta;
-        }
-        body.set_angvel(angvel, true);
-        self.game_object.physics_process(delta, body);
-    }
-
     fn update_state(&mut self, body: &RigidBody) {
         self.game_object.update_state(body);
     }
@@ -0,0 +1,199 @@
+//! Runs the simulation without a window (e.g. on a build server):
+//! `rayvarust --headless [--seed N] [--length N] [--fuel | --practice | --arcade | --clear] [--seconds N]`
+//! `[--ship NAME] [--load FILE] [--save FILE] [--gamepad LX,LY,RX,LT,RT] [--boost] [--fire] [--shield]`
+//! `[--record FILE]` or `rayvarust --headless --verify FILE`
+//!
+//! `--ship` is the name of a file in `resources/ships` (without extension),
+//! `--load` continues from a snapshot instead of generating a level,
+//! `--save` writes a snapshot of the state the run ended in,
+//! `--gamepad` holds sticks and triggers of a made up gamepad for the whole run,
+//! `--boost` holds the afterburner along with it, `--fire` the trigger of the gun
+//! and `--shield` the shield button (raising it whenever it's charged),
+//! `--record` saves a replay of the run (not with `--load`).
+//! `--verify` simulates a replay again and checks it ends the way its file claims
+//! (exits with 1 if it doesn't).
+
+use crate::controls::{GamepadSettings, GamepadState};
+use crate::ruleset::Ruleset;
+use crate::ships;
+use crate::simulation::{Replay, ShipInput, Simulation, Snapshot, TIMESTEP};
+
+use raylib::math::Vector2;
+
+pub fn run(args: &[String]) {
+    if let Some(path) = arg_value::<String>(args, "--verify") {
+        verify(&path);
+        return;
+    }
+
+    let length = arg_value(args, "--length").unwrap_or(6);
+    let seed = arg_value(args, "--seed").unwrap_or_else(|| Simulation::fixed_seed(length));
+    let seconds: f32 = arg_value(args, "--seconds").unwrap_or(60.0);
+    let ruleset = if args.iter().any(|arg| arg == "--fuel") {
+        Ruleset::fuel()
+    } else if args.iter().any(|arg| arg == "--practice") {
+        Ruleset::practice()
+    } else if args.iter().any(|arg| arg == "--arcade") {
+        Ruleset::arcade()
+    } else if args.iter().any(|arg| arg == "--clear") {
+        Ruleset::clear()
+    } else {
+        Ruleset::classic()
+    };
+
+    let load_path: Option<String> = arg_value(args, "--load");
+    let mut replay = None;
+    let mut sim = match &load_path {
+        Some(path) => match Snapshot::load(path) {
+            Ok(snapshot) => Simulation::from_snapshot(snapshot),
+            Err(err) => {
+                println!("Couldn't load snapshot {}: {}", path, err);
+                return;
+            }
+        },
+        None => {
+            let ships = ships::load_ships();
+            let ship = match arg_value::<String>(args, "--ship") {
+                Some(id) => match ships.iter().find(|ship| ship.id == id) {
+                    Some(ship) => ship,
+                    None => {
+                        println!("No ship \"{}\"", id);
+                        return;
+                    }
+                },
+                None => &ships[0],
+            };
+            let new_replay = Replay::new(seed, length, ruleset, &ship.id, ship.stats);
+            let sim = new_replay.start();
+            replay = Some(new_replay);
+            sim
+        }
+    };
+    let mut input = match arg_value::<String>(args, "--gamepad") {
+        Some(values) => match parse_gamepad(&values) {
+            Some(gamepad) => gamepad.ship_input(&GamepadSettings::default()),
+            None => {
+                println!("--gamepad needs 5 numbers: LX,LY,RX,LT,RT");
+                return;
+            }
+        },
+        None => ShipInput::default(),
+    };
+    input.boost = args.iter().any(|arg| arg == "--boost");
+    input.fire = args.iter().any(|arg| arg == "--fire");
+    input.shield = args.iter().any(|arg| arg == "--shield");
+    let steps = (seconds / TIMESTEP).round() as u32;
+    for _ in 0..steps {
+        if let Some(replay) = replay.as_mut() {
+            replay.record(&sim, &input);
+        }
+        sim.step(&input);
+        if sim.is_completed() {
+            break;
+        }
+    }
+
+    if let (Some(path), Some(replay)) = (arg_value::<String>(args, "--record"), replay.as_mut()) {
+        replay.finish(&sim);
+        if let Err(err) = replay.save(&path) {
+            println!("Couldn't save replay {}: {}", path, err);
+        }
+    }
+
+    if let Some(path) = arg_value::<String>(args, "--save") {
+        if let Err(err) = sim.snapshot().save(&path) {
+            println!("Couldn't save snapshot {}: {}", path, err);
+        }
+    }
+
+    match &load_path {
+        Some(path) => println!("Loaded: {}", path),
+        None => println!("Seed: {}, length: {}", seed, length),
+    }
+    print_summary(&sim);
+}
+
+/// Checks a replay by simulating it again
+fn verify(path: &str) {
+    let replay = match Replay::load(path) {
+        Ok(replay) => replay,
+        Err(err) => {
+            println!("Couldn't load replay {}: {}", path, err);
+            std::process::exit(1);
+        }
+    };
+    println!(
+        "Replay: {} (seed: {}, length: {}, {}, ship: {}, {} steps)",
+        path,
+        replay.seed,
+        replay.length,
+        replay.ruleset.name(),
+        replay.ship_id,
+        replay.len()
+    );
+    match replay.verify() {
+        Ok(result) => {
+            print_summary(&replay.resimulate());
+            println!("Verified: time {:.2}, score {}", result.time, result.score);
+        }
+        Err(err) => {
+            println!("{}", err);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Parses "LX,LY,RX,LT,RT" (sticks from -1 to 1, triggers from 0 to 1)
+fn parse_gamepad(values: &str) -> Option<GamepadState> {
+    let values: Vec<f32> = values
+        .split(',')
+        .map(|value| value.trim().parse())
+        .collect::<Result<_, _>>()
+        .ok()?;
+    match values[..] {
+        [lx, ly, rx, lt, rt] => Some(GamepadState {
+            left_stick: Vector2::new(lx, ly),
+            right_stick: Vector2::new(rx, 0.0),
+            left_trigger: lt,
+            right_trigger: rt,
+        }),
+        _ => None,
+    }
+}
+
+/// Parses the value following given flag
+fn arg_value<T: std::str::FromStr>(args: &[String], flag: &str) -> Option<T> {
+    let index = args.iter().position(|arg| arg == flag)?;
+    args.get(index + 1)?.parse().ok()
+}
+
+pub fn print_summary(sim: &Simulation) {
+    let result = if !sim.is_completed() {
+        "unfinished"
+    } else if sim.is_failed() {
+        "failed"
+    } else {
+        "completed"
+    };
+    println!("Result: {}", result);
+    println!("Score: {}", sim.score());
+    println!("Gates: {}/{}", sim.next_gate(), sim.gate_count());
+    println!("Time: {:.2}", sim.time_since_start());
+    println!("{}", sim.stats().assists_summary());
+    if sim.ruleset().weapons {
+        println!("Asteroids shot: {}", sim.stats().asteroids_shot);
+    }
+    let visited = sim.landing_pads().iter().filter(|pad| pad.visited).count();
+    println!("Pads visited: {}/{}", visited, sim.landing_pads().len());
+    if let Some(ship) = sim.ship() {
+        let position = sim.bodies()[ship.body].translation();
+        println!("Ship position: ({:.1}, {:.1})", position.x, position.y);
+        if sim.ruleset().fuel_mode {
+            println!("Fuel: {:.0}", ship.fuel);
+            println!("Fuel collected: {:.0}", sim.stats().fuel_collected);
+            println!("{}", sim.stats().fuel_summary());
+            println!("RCS: {:.0}", ship.rcs);
+        }
+        println!("Heat: {:.2}", ship.heat);
+    }
+}
@@ -5,21 +5,34 @@ mod math;
 
 mod game_object;
 
+mod simulation;
+use simulation::{Replay, Simulation};
+
 mod game;
 use game::{Game, GameAction};
 
+mod headless;
+
 mod menu;
 use menu::{Menu, MenuAction};
 
-use rapier2d::prelude::*;
+mod ruleset;
+use ruleset::Ruleset;
 
-use rand::prelude::*;
+mod controls;
+use controls::Bindings;
 
-const SHIP_NAMES: [&str; 5] = ["sr", "sb", "sg", "sp", "sy"];
+mod ships;
 
-const FUEL_MULTIPLIER: f32 = 42.0;
+use rand::prelude::*;
 
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "--headless") {
+        headless::run(&args);
+        return;
+    }
+
     let window_width: i16 = 1920;
     let window_height: i16 = 1080;
     let (mut rl, thread) = raylib::init()
@@ -35,14 +48,65 @@ fn main() {
     let mut restart = false;
     let mut quit = false;
     let mut selected_length = 6;
-    let mut action = MenuAction::Start(selected_length, true, false);
+    let mut action = MenuAction::Start(selected_length, true, 0);
     let mut random_levels = false;
-    let mut fuel_mode = false;
+    let mut time_attack = false;
+    let mut selected_ruleset = 0;
     let mut seed = 0;
     let mut selected_ship = 0;
 
     let mut audio = RaylibAudio::init_audio_device();
 
+    let mut bindings = Bindings::load();
+    let ships = ships::load_ships();
+
+    // Only watching a replay
+    if let Some(index) = args.iter().position(|arg| arg == "--replay") {
+        let path = match args.get(index + 1) {
+            Some(path) => path,
+            None => {
+                println!("--replay needs a file");
+                return;
+            }
+        };
+        let replay = match Replay::load(path) {
+            Ok(replay) => replay,
+            Err(err) => {
+                println!("Couldn't load replay {}: {}", path, err);
+                return;
+            }
+        };
+        // Drawn as the first ship if it's not around anymore (it flies the same)
+        let ship = ships
+            .iter()
+            .find(|ship| ship.id == replay.ship_id)
+            .unwrap_or(&ships[0])
+            .clone();
+        let window_width = rl.get_screen_width() as i16;
+        let window_height = rl.get_screen_height() as i16;
+        loop {
+            let mut the_game = Game::new(
+                &mut rl,
+                &thread,
+                &mut audio,
+                window_width,
+                window_height,
+                replay.clone(),
+                ship.clone(),
+                bindings.clone(),
+                false,
+                true,
+            );
+            let action = the_game.run();
+            the_game.unload();
+            drop(the_game);
+            // Restarting watches it again from the start
+            if action != GameAction::Restart {
+                return;
+            }
+        }
+    }
+
     while !quit {
         if !restart {
             let mut menu = Menu::new(
@@ -51,47 +115,54 @@ fn main() {
                 window_width,
                 window_height,
                 random_levels,
-                fuel_mode,
+                time_attack,
+                selected_ruleset,
                 selected_ship,
                 selected_length.into(),
+                bindings,
+                &ships,
             );
             action = menu.run();
             menu.unload();
             selected_ship = menu.selected_ship;
+            time_attack = menu.time_attack;
+            bindings = menu.bindings.clone();
             seed = 0;
         }
         restart = false;
 
         match action {
-            MenuAction::Start(length, random, fuel) => {
+            MenuAction::Start(length, random, ruleset_index) => {
                 selected_length = length;
                 random_levels = random;
-                fuel_mode = fuel;
+                selected_ruleset = ruleset_index;
+                let ruleset = Ruleset::from_index(selected_ruleset);
                 let window_width = rl.get_screen_width() as i16;
                 let window_height = rl.get_screen_height() as i16;
                 if seed == 0 {
                     seed = if random {
                         thread_rng().gen::<u16>() as u64
                     } else {
-                        ((length << 6) + 321) as u64
+                        Simulation::fixed_seed(length)
                     };
                 }
+                let ship = ships[selected_ship].clone();
+                let replay = Replay::new(seed, length, ruleset, &ship.id, ship.stats);
                 let mut the_game = Game::new(
                     &mut rl,
                     &thread,
                     &mut audio,
                     window_width,
                     window_height,
-                    seed,
-                    fuel_mode,
-                    selected_ship,
+                    replay,
+                    ship,
+                    bindings.clone(),
+                    time_attack,
+                    false,
                 );
 
-                the_game.spawn_many_planets_with_gates(length);
-
-                the_game.spawn_player(vector![0., 0.], FUEL_MULTIPLIER * length as f32);
-
                 let action = the_game.run();
+                bindings.camera_mode = the_game.camera_mode();
                 match action {
                     GameAction::Menu => {}
                     GameAction::Restart => {
@@ -5,6 +5,10 @@ use raylib::math::Vector2;
 use rapier2d::prelude::Real;
 /// Vector2 from nalgebra (so it doesnt collide with raylib's Vector2)
 pub type NVector2 = rapier2d::prelude::nalgebra::base::Vector2<Real>;
+/// Point2 from nalgebra
+pub type NPoint2 = rapier2d::prelude::Point<Real>;
+
+pub mod polygon;
 
 use std::ops::{Add, Mul, Sub};
 
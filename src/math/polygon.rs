@@ -0,0 +1,293 @@
+//! Convex polygon helpers (asteroid shapes and their fracturing)
+
+use super::{NPoint2, NVector2};
+
+use rand::prelude::*;
+
+use std::f32::consts::PI;
+
+/// Generates a random convex polygon centered on its centroid.
+/// Vertices are roughly `radius` away from the center, in counter-clockwise order
+pub fn random_convex(rng: &mut impl Rng, radius: f32) -> Vec<NPoint2> {
+    let vertex_count = rng.gen_range(7..12);
+    let angle_step = 2.0 * PI / vertex_count as f32;
+    let points: Vec<NPoint2> = (0..vertex_count)
+        .map(|i| {
+            let angle = angle_step * (i as f32 + rng.gen_range(-0.3..0.3));
+            let r = radius * rng.gen_range(0.7..1.0);
+            NPoint2::new(angle.cos() * r, angle.sin() * r)
+        })
+        .collect();
+    let hull = convex_hull(&points);
+    let center = centroid(&hull);
+    translated(&hull, -center.coords)
+}
+
+/// Convex hull of given points (Andrew's monotone chain), in counter-clockwise order
+pub fn convex_hull(points: &[NPoint2]) -> Vec<NPoint2> {
+    // Degenerate splits can produce NaN, those points are left out
+    let mut sorted: Vec<NPoint2> = points
+        .iter()
+        .filter(|p| p.x.is_finite() && p.y.is_finite())
+        .copied()
+        .collect();
+    sorted.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+    if sorted.len() < 3 {
+        return sorted;
+    }
+
+    let mut hull: Vec<NPoint2> = Vec::with_capacity(sorted.len() * 2);
+    // Lower hull
+    for p in sorted.iter() {
+        while hull.len() >= 2 && cross(hull[hull.len() - 2], hull[hull.len() - 1], *p) <= 0.0 {
+            hull.pop();
+        }
+        hull.push(*p);
+    }
+    // Upper hull
+    let lower_len = hull.len() + 1;
+    for p in sorted.iter().rev().skip(1) {
+        while hull.len() >= lower_len
+            && cross(hull[hull.len() - 2], hull[hull.len() - 1], *p) <= 0.0
+        {
+            hull.pop();
+        }
+        hull.push(*p);
+    }
+    // Last point is the same as the first one
+    hull.pop();
+    hull
+}
+
+/// Z component of (a - o) x (b - o)
+fn cross(o: NPoint2, a: NPoint2, b: NPoint2) -> f32 {
+    (a.x - o.x) * (b.y - o.y) - (a.y - o.y) * (b.x - o.x)
+}
+
+/// Segments a1-a2 and b1-b2 cross (or touch)
+pub fn segments_intersect(a1: NPoint2, a2: NPoint2, b1: NPoint2, b2: NPoint2) -> bool {
+    let d1 = cross(b1, b2, a1);
+    let d2 = cross(b1, b2, a2);
+    let d3 = cross(a1, a2, b1);
+    let d4 = cross(a1, a2, b2);
+    d1 * d2 <= 0.0 && d3 * d4 <= 0.0
+}
+
+/// Signed area, positive for counter-clockwise polygons
+pub fn signed_area(polygon: &[NPoint2]) -> f32 {
+    let mut area = 0.0;
+    for (i, a) in polygon.iter().enumerate() {
+        let b = polygon[(i + 1) % polygon.len()];
+        area += a.x * b.y - b.x * a.y;
+    }
+    area * 0.5
+}
+
+pub fn area(polygon: &[NPoint2]) -> f32 {
+    signed_area(polygon).abs()
+}
+
+/// Center of mass of a polygon with uniform density
+pub fn centroid(polygon: &[NPoint2]) -> NPoint2 {
+    let mut center = NVector2::zeros();
+    let mut cross_sum = 0.0;
+    for (i, a) in polygon.iter().enumerate() {
+        let b = polygon[(i + 1) % polygon.len()];
+        let cross = a.x * b.y - b.x * a.y;
+        cross_sum += cross;
+        center += (a.coords + b.coords) * cross;
+    }
+    if cross_sum.abs() < f32::EPSILON {
+        // Degenerate polygon, just average the points
+        let sum = polygon
+            .iter()
+            .fold(NVector2::zeros(), |sum, p| sum + p.coords);
+        return NPoint2::from(sum / polygon.len().max(1) as f32);
+    }
+    NPoint2::from(center / (3.0 * cross_sum))
+}
+
+pub fn translated(polygon: &[NPoint2], offset: NVector2) -> Vec<NPoint2> {
+    polygon.iter().map(|p| *p + offset).collect()
+}
+
+/// Splits a convex polygon with a line going through `point` along `direction`.
+/// Returns parts on both sides of the line (keeping the winding), either can be empty
+pub fn split(
+    polygon: &[NPoint2],
+    point: NPoint2,
+    direction: NVector2,
+) -> (Vec<NPoint2>, Vec<NPoint2>) {
+    let normal = NVector2::new(-direction.y, direction.x);
+    let side = |p: NPoint2| (p - point).dot(&normal);
+
+    let mut left = Vec::new();
+    let mut right = Vec::new();
+    for (i, a) in polygon.iter().enumerate() {
+        let b = polygon[(i + 1) % polygon.len()];
+        let side_a = side(*a);
+        let side_b = side(b);
+        if side_a >= 0.0 {
+            left.push(*a);
+        }
+        if side_a <= 0.0 {
+            right.push(*a);
+        }
+        // Edge crosses the line
+        if (side_a > 0.0 && side_b < 0.0) || (side_a < 0.0 && side_b > 0.0) {
+            let t = side_a / (side_a - side_b);
+            let p = *a + (b - *a) * t;
+            left.push(p);
+            right.push(p);
+        }
+    }
+    (left, right)
+}
+
+/// Cuts a convex polygon into (at most) `piece_count` convex pieces,
+/// which together cover exactly the whole polygon
+pub fn fracture(rng: &mut impl Rng, polygon: &[NPoint2], piece_count: usize) -> Vec<Vec<NPoint2>> {
+    let mut pieces = vec![polygon.to_vec()];
+    while pieces.len() < piece_count {
+        // Always cut the biggest piece
+        let mut biggest = 0;
+        for (i, piece) in pieces.iter().enumerate() {
+            if area(piece) > area(&pieces[biggest]) {
+                biggest = i;
+            }
+        }
+        let piece = pieces.swap_remove(biggest);
+
+        let angle = rng.gen_range(0.0..PI);
+        let direction = NVector2::new(angle.cos(), angle.sin());
+        let (left, right) = split(&piece, centroid(&piece), direction);
+        if left.len() < 3 || right.len() < 3 {
+            pieces.push(piece);
+            break;
+        }
+        pieces.push(left);
+        pieces.push(right);
+    }
+    pieces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand_pcg::Pcg64;
+
+    fn square() -> Vec<NPoint2> {
+        vec![
+            NPoint2::new(0.0, 0.0),
+            NPoint2::new(1.0, 0.0),
+            NPoint2::new(1.0, 1.0),
+            NPoint2::new(0.0, 1.0),
+        ]
+    }
+
+    #[test]
+    fn hull_drops_inner_and_collinear_points() {
+        let mut points = square();
+        points.push(NPoint2::new(0.5, 0.5));
+        points.push(NPoint2::new(0.5, 0.0));
+        let hull = convex_hull(&points);
+        assert_eq!(hull.len(), 4);
+        assert!(signed_area(&hull) > 0.0, "hull should be counter-clockwise");
+        assert!((area(&hull) - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn hull_ignores_non_finite_points() {
+        let mut points = square();
+        points.push(NPoint2::new(f32::NAN, 0.5));
+        points.push(NPoint2::new(0.5, f32::INFINITY));
+        let hull = convex_hull(&points);
+        assert_eq!(hull.len(), 4);
+        assert!(hull.iter().all(|p| p.x.is_finite() && p.y.is_finite()));
+    }
+
+    #[test]
+    fn hull_of_few_points() {
+        assert!(convex_hull(&[]).is_empty());
+        let two = [NPoint2::new(1.0, 0.0), NPoint2::new(0.0, 0.0)];
+        assert_eq!(convex_hull(&two), vec![two[1], two[0]]);
+    }
+
+    #[test]
+    fn crossing_and_touching_segments_intersect() {
+        let (a, b) = (NPoint2::new(0.0, 0.0), NPoint2::new(2.0, 2.0));
+        assert!(segments_intersect(
+            a,
+            b,
+            NPoint2::new(0.0, 2.0),
+            NPoint2::new(2.0, 0.0)
+        ));
+        // End on the other segment
+        assert!(segments_intersect(
+            a,
+            b,
+            NPoint2::new(1.0, 1.0),
+            NPoint2::new(2.0, 0.0)
+        ));
+    }
+
+    #[test]
+    fn separate_segments_dont_intersect() {
+        let (a, b) = (NPoint2::new(0.0, 0.0), NPoint2::new(2.0, 0.0));
+        assert!(!segments_intersect(
+            a,
+            b,
+            NPoint2::new(0.0, 1.0),
+            NPoint2::new(2.0, 1.0)
+        ));
+        // Line would cross, segment stops short
+        assert!(!segments_intersect(
+            a,
+            b,
+            NPoint2::new(1.0, 2.0),
+            NPoint2::new(1.0, 0.5)
+        ));
+    }
+
+    #[test]
+    fn split_keeps_area_and_winding() {
+        let square = square();
+        let (left, right) = split(&square, NPoint2::new(0.5, 0.5), NVector2::new(1.0, 1.0));
+        assert_eq!((left.len(), right.len()), (3, 3));
+        assert!(signed_area(&left) > 0.0 && signed_area(&right) > 0.0);
+        assert!((area(&left) + area(&right) - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn split_outside_leaves_one_side_empty() {
+        let square = square();
+        let (left, right) = split(&square, NPoint2::new(0.0, 5.0), NVector2::new(1.0, 0.0));
+        assert!(left.is_empty());
+        assert_eq!(right.len(), 4);
+    }
+
+    #[test]
+    fn fracture_covers_the_whole_polygon() {
+        let mut rng = Pcg64::seed_from_u64(7);
+        for _ in 0..20 {
+            let polygon = random_convex(&mut rng, 100.0);
+            let pieces = fracture(&mut rng, &polygon, 4);
+            assert!(!pieces.is_empty() && pieces.len() <= 4);
+            let total: f32 = pieces.iter().map(|piece| area(piece)).sum();
+            assert!((total - area(&polygon)).abs() < area(&polygon) * 1e-3);
+            for piece in pieces.iter() {
+                assert!(piece.len() >= 3);
+                assert!(signed_area(piece) > 0.0);
+            }
+        }
+    }
+
+    #[test]
+    fn random_convex_is_centered() {
+        let mut rng = Pcg64::seed_from_u64(1);
+        let polygon = random_convex(&mut rng, 50.0);
+        assert!(polygon.len() >= 3);
+        assert!(centroid(&polygon).coords.norm() < 1e-3);
+    }
+}
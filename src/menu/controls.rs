@@ -0,0 +1,155 @@
+use raylib::prelude::*;
+use std::ffi::CString;
+
+use crate::controls::{key_name, Action, Bindings};
+
+/// Size of the controls screen
+const SIZE: Vector2 = Vector2 { x: 760., y: 820. };
+
+/// Screen for changing key bindings
+pub struct ControlsScreen {
+    pub open: bool,
+    /// Action waiting for a key to be pressed
+    pub rebinding: Option<Action>,
+    /// Gamepad settings were changed but not saved yet
+    settings_changed: bool,
+    /// What the last rebind did to another action
+    message: Option<String>,
+}
+
+impl ControlsScreen {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            rebinding: None,
+            settings_changed: false,
+            message: None,
+        }
+    }
+
+    /// Binds `key` to the action waiting for it and saves the bindings (Esc cancels).
+    /// An action that already had the key gets the old key of this one
+    pub fn bind(&mut self, key: KeyboardKey, bindings: &mut Bindings) {
+        let action = match self.rebinding.take() {
+            Some(action) if key != KeyboardKey::KEY_ESCAPE => action,
+            _ => return,
+        };
+        let old_key = bindings.key(action);
+        let other = Action::ALL
+            .iter()
+            .copied()
+            .find(|other| *other != action && bindings.key(*other) == key);
+        if let Some(other) = other {
+            bindings.set(other, old_key);
+            self.message = Some(format!("{} moved to {}", other.name(), key_name(old_key)));
+        }
+        bindings.set(action, key);
+        save(bindings);
+    }
+
+    pub fn draw(&mut self, d: &mut RaylibDrawHandle, center: Vector2, bindings: &mut Bindings) {
+        let corner = center - SIZE * 0.5;
+        d.draw_rectangle_v(corner, SIZE, Color::DARKPURPLE.fade(0.95));
+        d.gui_label(
+            rrect(corner.x + 20., corner.y + 10., 500., 30.),
+            Some(rstr!("Click an action, then press a key (Esc cancels)")),
+        );
+
+        // Two columns of actions
+        let rows = (Action::ALL.len() + 1) / 2;
+        for (i, action) in Action::ALL.iter().enumerate() {
+            let column = (i / rows) as f32;
+            let row = (i % rows) as f32;
+            let key = if self.rebinding == Some(*action) {
+                "..."
+            } else {
+                key_name(bindings.key(*action))
+            };
+            let text = CString::new(format!("{}: {}", action.name(), key)).unwrap();
+            let bounds = rrect(
+                corner.x + 20. + column * 370.,
+                corner.y + 50. + row * 34.,
+                350.,
+                30.,
+            );
+            if d.gui_button(bounds, Some(&text)) {
+                self.rebinding = Some(*action);
+                self.message = None;
+            }
+        }
+
+        // Gamepad and mouse settings
+        let settings = &mut bindings.gamepad;
+        let old_settings = *settings;
+        let top = corner.y + 50. + rows as f32 * 34. + 10.;
+        let dead_zone_text = CString::new(format!("{:.2}", settings.dead_zone)).unwrap();
+        settings.dead_zone = d.gui_slider(
+            rrect(corner.x + 260., top, 300., 30.),
+            Some(rstr!("Gamepad dead zone")),
+            Some(&dead_zone_text),
+            settings.dead_zone,
+            0.0,
+            0.5,
+        );
+        let sensitivity_text = CString::new(format!("{:.2}", settings.sensitivity)).unwrap();
+        settings.sensitivity = d.gui_slider(
+            rrect(corner.x + 260., top + 40., 300., 30.),
+            Some(rstr!("Gamepad sensitivity")),
+            Some(&sensitivity_text),
+            settings.sensitivity,
+            0.5,
+            2.0,
+        );
+        // Saved when the slider is let go
+        if *settings != old_settings {
+            self.settings_changed = true;
+        }
+        if self.settings_changed && !d.is_mouse_button_down(MouseButton::MOUSE_LEFT_BUTTON) {
+            self.settings_changed = false;
+            save(bindings);
+        }
+
+        let mouse_text = if bindings.mouse_steering {
+            rstr!("Mouse steering: ON")
+        } else {
+            rstr!("Mouse steering: OFF")
+        };
+        let mouse_steering = d.gui_toggle(
+            rrect(corner.x + 20., top + 80., 240., 30.),
+            Some(mouse_text),
+            bindings.mouse_steering,
+        );
+        if mouse_steering != bindings.mouse_steering {
+            bindings.mouse_steering = mouse_steering;
+            save(bindings);
+        }
+
+        let bottom = corner.y + SIZE.y - 50.;
+        if d.gui_button(
+            rrect(corner.x + 20., bottom, 170., 36.),
+            Some(rstr!("Reset to defaults")),
+        ) {
+            *bindings = Bindings::default();
+            self.rebinding = None;
+            self.message = None;
+            save(bindings);
+        }
+        if let Some(message) = &self.message {
+            let text = CString::new(message.as_str()).unwrap();
+            d.gui_label(rrect(corner.x + 210., bottom, 400., 36.), Some(&text));
+        }
+        if d.gui_button(
+            rrect(corner.x + SIZE.x - 140., bottom, 120., 36.),
+            Some(rstr!("Done")),
+        ) {
+            self.open = false;
+            self.rebinding = None;
+        }
+    }
+}
+
+fn save(bindings: &Bindings) {
+    if let Err(err) = bindings.save() {
+        println!("Couldn't save controls: {}", err);
+    }
+}
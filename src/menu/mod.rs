@@ -1,11 +1,19 @@
 use raylib::prelude::*;
 use std::ffi::CString;
 
-use crate::SHIP_NAMES;
+use crate::controls::{Bindings, GAMEPAD};
+use crate::ruleset::RULESET_NAMES;
+use crate::ships::ShipDef;
 
 mod button;
 use button::Button;
 
+mod controls;
+use controls::ControlsScreen;
+
+mod ship_stats;
+use ship_stats::draw_ship_stats;
+
 pub struct Menu<'a> {
     rl: &'a mut RaylibHandle,
     thread: &'a RaylibThread,
@@ -13,30 +21,30 @@ pub struct Menu<'a> {
     center: Vector2,
     bg_tex: Texture2D,
     start_button: Button,
+    controls_button: Button,
     quit_button: Button,
     selected_length: f32,
     font: Font,
     random_levels: bool,
-    fuel_mode: bool,
+    /// Race against a ghost of the best run
+    pub time_attack: bool,
+    selected_ruleset: usize,
     pub selected_ship: usize,
     ship_prev: Button,
     ship_next: Button,
+    ships: &'a [ShipDef],
     ship_textures: Vec<WeakTexture2D>,
     popup_texture: Texture2D,
     popup_open: bool,
     popup_button: Button,
+    pub bindings: Bindings,
+    controls: ControlsScreen,
 }
 
 const SHIP_SELECT_POS: Vector2 = Vector2 { x: 0.1, y: 0.4 };
 const POPUP_POS: Vector2 = Vector2 { x: 0.26, y: 0.27 };
 
-const POPUP_TEXT: &str = "
-Controls:
-  WSAD - acceleration
-  IO - rotation
-  KL - zoom in/out
-  Tab - pause/unpause
-Rules:
+const RULES_TEXT: &str = "Rules:
   Go through a gate = +30 points
   Touch anything = -10 points
   Have less than 0 points = fail
@@ -51,9 +59,12 @@ impl<'a> Menu<'a> {
         window_width: i16,
         window_height: i16,
         random_levels: bool,
-        fuel_mode: bool,
+        time_attack: bool,
+        selected_ruleset: usize,
         selected_ship: usize,
         selected_length: f32,
+        bindings: Bindings,
+        ships: &'a [ShipDef],
     ) -> Self {
         rl.show_cursor();
         let center = rvec2((window_width / 2) as f32, (window_height / 2) as f32);
@@ -65,6 +76,12 @@ impl<'a> Menu<'a> {
             center + rvec2(0., 50. * line),
         );
         line += 1.;
+        let controls_button = Button::new(
+            "Controls".to_string(),
+            rvec2(120., 40.),
+            center + rvec2(0., 50. * line),
+        );
+        line += 1.;
         let quit_button = Button::new(
             "Quit".to_string(),
             rvec2(120., 40.),
@@ -99,11 +116,11 @@ impl<'a> Menu<'a> {
             .expect("Couldn't load font");
 
         let mut ship_textures = vec![];
-        for ship_name in SHIP_NAMES {
+        for ship in ships {
             let tex = rl
                 .load_texture(
                     thread,
-                    &("resources/textures/ships/".to_owned() + ship_name + ".png"),
+                    &("resources/textures/ships/".to_owned() + &ship.texture + ".png"),
                 )
                 .unwrap();
             let weak = unsafe { tex.make_weak() };
@@ -126,17 +143,22 @@ impl<'a> Menu<'a> {
             bg_tex,
             selected_length,
             start_button,
+            controls_button,
             quit_button,
             font,
             random_levels,
-            fuel_mode,
+            time_attack,
+            selected_ruleset,
             selected_ship,
             ship_prev,
             ship_next,
+            ships,
             ship_textures,
             popup_texture,
             popup_open: false,
             popup_button,
+            bindings,
+            controls: ControlsScreen::new(),
         }
     }
 
@@ -152,6 +174,8 @@ impl<'a> Menu<'a> {
             let mut line = 0.;
             self.start_button.position = self.center + rvec2(0., 50. * line);
             line += 1.;
+            self.controls_button.position = self.center + rvec2(0., 50. * line);
+            line += 1.;
             self.quit_button.position = self.center + rvec2(0., 50. * line);
             self.ship_prev.position =
                 SHIP_SELECT_POS * rvec2(window_width, window_height) + rvec2(90.0, 280.);
@@ -161,7 +185,42 @@ impl<'a> Menu<'a> {
                 (POPUP_POS + rvec2(0.468, 0.546)) * rvec2(window_width, window_height);
         }
 
-        let esc_pressed = self.rl.is_key_pressed(KeyboardKey::KEY_ESCAPE);
+        // Key for the action being rebound
+        if self.controls.rebinding.is_some() {
+            if let Some(key) = self.rl.get_key_pressed() {
+                self.controls.bind(key, &mut self.bindings);
+                // So the key doesn't do anything else
+                return None;
+            }
+        }
+
+        let mut esc_pressed = self.rl.is_key_pressed(KeyboardKey::KEY_ESCAPE);
+        // Esc closes the controls screen first
+        if esc_pressed && self.controls.open {
+            self.controls.open = false;
+            esc_pressed = false;
+        }
+
+        // Gamepad navigation (B closes whatever is open)
+        let rl = &*self.rl;
+        let pad = |button| rl.is_gamepad_button_pressed(GAMEPAD, button);
+        if pad(GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_RIGHT) {
+            self.controls.open = false;
+            self.popup_open = false;
+        }
+        let pad = if self.popup_open || self.controls.open {
+            MenuPad::default()
+        } else {
+            MenuPad {
+                ship_prev: pad(GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_LEFT),
+                ship_next: pad(GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_RIGHT),
+                longer: pad(GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_UP),
+                shorter: pad(GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_DOWN),
+                random: pad(GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_LEFT),
+                ruleset: pad(GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_UP),
+                start: pad(GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_DOWN),
+            }
+        };
 
         let mut d = self.rl.begin_drawing(self.thread);
         d.clear_background(Color::DARKPURPLE);
@@ -199,16 +258,16 @@ impl<'a> Menu<'a> {
 
         // Buttons for selecting ship
         let ship_p = self.ship_prev.draw(&mut d);
-        if ship_p {
+        if ship_p || pad.ship_prev {
             if self.selected_ship == 0 {
-                self.selected_ship = SHIP_NAMES.len();
+                self.selected_ship = self.ships.len();
             }
             self.selected_ship -= 1;
         }
         let ship_n = self.ship_next.draw(&mut d);
-        if ship_n {
+        if ship_n || pad.ship_next {
             self.selected_ship += 1;
-            if self.selected_ship >= SHIP_NAMES.len() {
+            if self.selected_ship >= self.ships.len() {
                 self.selected_ship = 0;
             }
         }
@@ -219,9 +278,16 @@ impl<'a> Menu<'a> {
             SHIP_SELECT_POS * rvec2(self.window_size.0, self.window_size.1),
             Color::WHITE,
         );
+        draw_ship_stats(
+            &mut d,
+            &self.font,
+            self.ships,
+            self.selected_ship,
+            SHIP_SELECT_POS * rvec2(self.window_size.0, self.window_size.1) + rvec2(0., 320.),
+        );
 
         // Disable guis under popup
-        if self.popup_open {
+        if self.popup_open || self.controls.open {
             d.gui_disable();
         }
 
@@ -240,21 +306,39 @@ impl<'a> Menu<'a> {
             Some(toggle_text),
             self.random_levels,
         );
+        self.random_levels ^= pad.random;
 
-        // Fuel mode toggle
-        let mut toggle_text = rstr!("Fuel mode: ON");
-        if !self.fuel_mode {
-            toggle_text = rstr!("Fuel mode: OFF");
-        }
-        self.fuel_mode = d.gui_toggle(
+        // Ruleset selection
+        let ruleset_names = CString::new(RULESET_NAMES.join(";")).unwrap();
+        self.selected_ruleset = d.gui_toggle_group(
             rrect(
                 0.625 * self.window_size.0 as f32,
                 0.65 * self.window_size.1 as f32 + 60.0,
+                96.,
+                50.,
+            ),
+            Some(&ruleset_names),
+            self.selected_ruleset as i32,
+        ) as usize;
+        if pad.ruleset {
+            self.selected_ruleset = (self.selected_ruleset + 1) % RULESET_NAMES.len();
+        }
+
+        // Time attack toggle
+        let toggle_text = if self.time_attack {
+            rstr!("Ghost race: ON")
+        } else {
+            rstr!("Ghost race: OFF")
+        };
+        self.time_attack = d.gui_toggle(
+            rrect(
+                0.625 * self.window_size.0 as f32,
+                0.65 * self.window_size.1 as f32 + 120.0,
                 200.,
                 50.,
             ),
             Some(toggle_text),
-            self.fuel_mode,
+            self.time_attack,
         );
 
         d.gui_label(
@@ -282,17 +366,28 @@ impl<'a> Menu<'a> {
                 32.,
             )
             .round();
+        if pad.longer {
+            self.selected_length = (self.selected_length + 1.).min(32.);
+        }
+        if pad.shorter {
+            self.selected_length = (self.selected_length - 1.).max(6.);
+        }
 
         // Start level
         let start = self.start_button.draw(&mut d);
-        if start {
+        if start || pad.start {
             return Some(MenuAction::Start(
                 self.selected_length as u16,
                 self.random_levels,
-                self.fuel_mode,
+                self.selected_ruleset,
             ));
         }
 
+        // Open controls screen
+        if self.controls_button.draw(&mut d) {
+            self.controls.open = true;
+        }
+
         // Quit game
         let quit_b_pressed = self.quit_button.draw(&mut d);
         if quit_b_pressed || esc_pressed {
@@ -314,9 +409,10 @@ impl<'a> Menu<'a> {
             );
 
             // Draw popup text
+            let popup_text = format!("\n{}{}", self.bindings.help_text(), RULES_TEXT);
             d.draw_text_ex(
                 &self.font,
-                POPUP_TEXT,
+                &popup_text,
                 POPUP_POS * rvec2(self.window_size.0, self.window_size.1) + rvec2(45., -10.),
                 30.0,
                 0.0,
@@ -333,6 +429,10 @@ impl<'a> Menu<'a> {
             );
         }
 
+        if self.controls.open {
+            self.controls.draw(&mut d, self.center, &mut self.bindings);
+        }
+
         None
     }
 
@@ -355,8 +455,21 @@ impl<'a> Menu<'a> {
     }
 }
 
+/// Menu buttons pressed on the gamepad this frame
+#[derive(Default)]
+struct MenuPad {
+    ship_prev: bool,
+    ship_next: bool,
+    longer: bool,
+    shorter: bool,
+    random: bool,
+    ruleset: bool,
+    start: bool,
+}
+
 #[derive(PartialEq, Eq)]
 pub enum MenuAction {
-    Start(u16, bool, bool),
+    /// Level length, random levels, index of the ruleset
+    Start(u16, bool, usize),
     Quit,
 }
@@ -0,0 +1,43 @@
+use raylib::prelude::*;
+
+use crate::ships::ShipDef;
+use crate::simulation::ShipStats;
+
+const BAR_WIDTH: f32 = 160.;
+const LINE_HEIGHT: f32 = 28.;
+
+/// Stats shown in the menu, as (name, how to get it from the stats)
+const STATS: [(&str, fn(&ShipStats) -> f32); 6] = [
+    ("Mass", |s| s.mass),
+    ("Acceleration", |s| s.main_thrust / s.mass),
+    ("Strafing", |s| s.side_thrust / s.mass),
+    ("Turning", |s| s.rotation_torque / s.angular_inertia()),
+    ("Fuel tank", |s| s.fuel_capacity),
+    ("Fuel use", |s| s.burn_rate),
+];
+
+/// Name of the selected ship and bars comparing its stats to the best ship's
+pub fn draw_ship_stats(
+    d: &mut RaylibDrawHandle,
+    font: &Font,
+    ships: &[ShipDef],
+    selected: usize,
+    position: Vector2,
+) {
+    let ship = &ships[selected];
+    d.draw_text_ex(font, &ship.name, position, 34., 0., Color::RAYWHITE);
+
+    for (i, (name, stat)) in STATS.iter().enumerate() {
+        let line_position = position + rvec2(0., 44. + i as f32 * LINE_HEIGHT);
+        d.draw_text_ex(font, name, line_position, 24., 0., Color::RAYWHITE);
+
+        let max = ships
+            .iter()
+            .map(|ship| stat(&ship.stats))
+            .fold(f32::EPSILON, f32::max);
+        let bar_position = line_position + rvec2(150., 4.);
+        let fill = stat(&ship.stats) / max * BAR_WIDTH;
+        d.draw_rectangle_v(bar_position, rvec2(BAR_WIDTH, 18.), Color::DARKGRAY);
+        d.draw_rectangle_v(bar_position, rvec2(fill, 18.), Color::GOLD);
+    }
+}
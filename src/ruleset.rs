@@ -0,0 +1,137 @@
+use crate::simulation::{Assists, CollisionGroup, CollisionMasks, FlightModel};
+
+use serde::{Deserialize, Serialize};
+
+/// Names of the rulesets selectable in the menu (in `Ruleset::from_index` order)
+pub const RULESET_NAMES: [&str; 5] = ["Classic", "Fuel", "Practice", "Arcade", "Clear"];
+
+/// What finishes a level
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Objective {
+    /// Flying through every gate
+    Gates,
+    /// Shooting this many asteroids
+    ClearField(u32),
+}
+
+/// Rules a level is played with
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Ruleset {
+    /// Ship has limited fuel
+    pub fuel_mode: bool,
+    /// Multiplies fuel used by every thruster (in fuel mode)
+    pub fuel_consumption: f32,
+    /// Rotating uses fuel from the RCS tank (in fuel mode)
+    pub limited_rcs: bool,
+    /// Max number of asteroids flying around at once
+    pub asteroid_budget: usize,
+    /// Seconds between spawning asteroids around the ship
+    pub asteroid_spawn_interval: f32,
+    /// Asteroids spawn between this and twice this distance from the ship
+    /// (further if the frontend's view is bigger)
+    pub asteroid_spawn_radius: f32,
+    /// Asteroids further than this from the ship get despawned (unless it's in view)
+    pub asteroid_despawn_radius: f32,
+    /// How many times the ship can rewind (`None` means unlimited)
+    pub rewind_limit: Option<u32>,
+    /// Points taken for every rewind
+    pub rewind_cost: i32,
+    /// How far back the ship can rewind (in seconds)
+    pub rewind_seconds: f32,
+    /// Flight assists the pilot may switch on
+    pub assists: Assists,
+    /// Flight model for every ship (`None` means each ship uses its own)
+    pub flight_model: Option<FlightModel>,
+    /// Ship can shoot
+    pub weapons: bool,
+    pub objective: Objective,
+    /// What collides with what
+    pub collisions: CollisionMasks,
+}
+
+impl Ruleset {
+    pub fn classic() -> Self {
+        Ruleset {
+            fuel_mode: false,
+            fuel_consumption: 1.0,
+            limited_rcs: true,
+            asteroid_budget: 200,
+            asteroid_spawn_interval: 0.4,
+            asteroid_spawn_radius: 3700.0,
+            asteroid_despawn_radius: 9000.0,
+            rewind_limit: Some(3),
+            rewind_cost: 10,
+            rewind_seconds: 5.0,
+            assists: Assists::all(),
+            flight_model: None,
+            weapons: false,
+            objective: Objective::Gates,
+            collisions: CollisionMasks::default(),
+        }
+    }
+
+    pub fn fuel() -> Self {
+        Ruleset {
+            fuel_mode: true,
+            // Only the one that doesn't fly for the pilot
+            assists: Assists {
+                rotation_damping: true,
+                ..Assists::default()
+            },
+            ..Self::classic()
+        }
+    }
+
+    /// For learning a level: rewinding is free and unlimited
+    pub fn practice() -> Self {
+        Ruleset {
+            rewind_limit: None,
+            rewind_cost: 0,
+            rewind_seconds: 10.0,
+            weapons: true,
+            ..Self::classic()
+        }
+    }
+
+    /// Forgiving handling: ships slow down by themselves and can't go too fast
+    pub fn arcade() -> Self {
+        Ruleset {
+            flight_model: Some(FlightModel::Arcade),
+            weapons: true,
+            ..Self::classic()
+        }
+    }
+
+    /// Level is done after shooting enough asteroids, gates don't matter
+    pub fn clear() -> Self {
+        Ruleset {
+            weapons: true,
+            objective: Objective::ClearField(40),
+            // Shots fly past gates
+            collisions: CollisionMasks::default()
+                .ignoring(CollisionGroup::Projectile, CollisionGroup::GateSolid),
+            ..Self::classic()
+        }
+    }
+
+    /// Name from `RULESET_NAMES` ("Custom" if it doesn't match any)
+    pub fn name(&self) -> &'static str {
+        RULESET_NAMES
+            .iter()
+            .enumerate()
+            .find(|(index, _)| Self::from_index(*index) == *self)
+            .map(|(_, name)| *name)
+            .unwrap_or("Custom")
+    }
+
+    /// Ruleset named `RULESET_NAMES[index]`
+    pub fn from_index(index: usize) -> Self {
+        match index {
+            1 => Self::fuel(),
+            2 => Self::practice(),
+            3 => Self::arcade(),
+            4 => Self::clear(),
+            _ => Self::classic(),
+        }
+    }
+}
@@ -0,0 +1,70 @@
+//! Ship definitions, loaded from files in `SHIPS_DIR` at startup
+
+use crate::simulation::ShipStats;
+
+use serde::Deserialize;
+
+use std::fs;
+use std::path::Path;
+
+const SHIPS_DIR: &str = "resources/ships";
+
+#[derive(Clone, Deserialize)]
+pub struct ShipDef {
+    /// Name of the file (without extension)
+    #[serde(skip)]
+    pub id: String,
+    pub name: String,
+    /// Texture in `resources/textures/ships/` (without extension)
+    pub texture: String,
+    pub sprite_scale: f32,
+    /// Where exhaust is drawn (in ship's local space)
+    pub exhaust_offset: (f32, f32),
+    pub stats: ShipStats,
+}
+
+impl Default for ShipDef {
+    fn default() -> Self {
+        ShipDef {
+            id: "default".to_string(),
+            name: "Default".to_string(),
+            texture: "sr".to_string(),
+            sprite_scale: 0.35,
+            exhaust_offset: (0.0, 50.0),
+            stats: ShipStats::default(),
+        }
+    }
+}
+
+/// Loads all ship definitions (sorted by file name).
+/// There's always at least one ship, even if no file could be loaded
+pub fn load_ships() -> Vec<ShipDef> {
+    let mut paths: Vec<_> = match fs::read_dir(SHIPS_DIR) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().map(|ext| ext == "ron").unwrap_or(false))
+            .collect(),
+        Err(err) => {
+            println!("Couldn't read {}: {}", SHIPS_DIR, err);
+            Vec::new()
+        }
+    };
+    paths.sort();
+
+    let mut ships: Vec<ShipDef> = paths.iter().filter_map(|path| load_ship(path)).collect();
+    if ships.is_empty() {
+        ships.push(ShipDef::default());
+    }
+    ships
+}
+
+fn load_ship(path: &Path) -> Option<ShipDef> {
+    let text = fs::read_to_string(path)
+        .map_err(|err| println!("Couldn't read {}: {}", path.display(), err))
+        .ok()?;
+    let mut ship: ShipDef = ron::from_str(&text)
+        .map_err(|err| println!("Couldn't parse {}: {}", path.display(), err))
+        .ok()?;
+    ship.id = path.file_stem()?.to_string_lossy().into_owned();
+    Some(ship)
+}
@@ -0,0 +1,181 @@
+//! Flight computer: adds thruster input on top of the pilot's to help with flying
+
+use super::{gravity_at, ShipInput, Simulation};
+
+use crate::math::NVector2;
+
+use rapier2d::prelude::*;
+
+use serde::{Deserialize, Serialize};
+
+use std::cmp::Ordering;
+use std::f32::consts::PI;
+
+/// Gains of the PD controller turning the ship toward a direction
+const TURN_P: f32 = 1.5;
+const TURN_D: f32 = 2.0;
+/// How hard the ship brakes spinning (per rad/s)
+const DAMPING_GAIN: f32 = 1.5;
+
+/// Which way the ship keeps its nose pointed
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Orientation {
+    /// Along the velocity relative to the nearest planet
+    Prograde,
+    /// Against the velocity relative to the nearest planet (ready to brake)
+    Retrograde,
+    /// At the next gate
+    NextGate,
+}
+
+impl Orientation {
+    pub fn name(self) -> &'static str {
+        match self {
+            Orientation::Prograde => "prograde",
+            Orientation::Retrograde => "retrograde",
+            Orientation::NextGate => "next gate",
+        }
+    }
+
+    /// Next one when cycling through them with one key (after the last one it's off)
+    pub fn cycle(orientation: Option<Self>) -> Option<Self> {
+        match orientation {
+            None => Some(Orientation::Prograde),
+            Some(Orientation::Prograde) => Some(Orientation::Retrograde),
+            Some(Orientation::Retrograde) => Some(Orientation::NextGate),
+            Some(Orientation::NextGate) => None,
+        }
+    }
+}
+
+/// Assists switched on. Also used by rulesets as the set of allowed ones
+#[derive(Clone, Copy, Default, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Assists {
+    /// Kills spinning when the pilot doesn't rotate
+    pub rotation_damping: bool,
+    /// Turns the ship when the pilot doesn't rotate
+    pub orientation: Option<Orientation>,
+    /// Counters gravity with thrusters (using fuel)
+    pub hold_velocity: bool,
+}
+
+impl Assists {
+    pub fn all() -> Self {
+        Assists {
+            rotation_damping: true,
+            orientation: Some(Orientation::Prograde),
+            hold_velocity: true,
+        }
+    }
+
+    pub fn any(&self) -> bool {
+        self.rotation_damping || self.orientation.is_some() || self.hold_velocity
+    }
+
+    /// Only the assists that are also in `allowed`
+    pub fn allowed(&self, allowed: &Assists) -> Self {
+        Assists {
+            rotation_damping: self.rotation_damping && allowed.rotation_damping,
+            orientation: self.orientation.filter(|_| allowed.orientation.is_some()),
+            hold_velocity: self.hold_velocity && allowed.hold_velocity,
+        }
+    }
+}
+
+/// Rotation input (-1.0 is full left, 1.0 full right) turning the ship toward `direction`.
+/// Ship's nose points along -Y when its angle is 0
+pub fn turn_toward(angle: f32, angvel: f32, direction: NVector2) -> f32 {
+    if direction.norm_squared() < 1.0 {
+        return 0.0;
+    }
+    let target = direction.x.atan2(-direction.y);
+    // Shortest way around
+    let mut error = (target - angle) % (2.0 * PI);
+    if error > PI {
+        error -= 2.0 * PI;
+    } else if error < -PI {
+        error += 2.0 * PI;
+    }
+    (TURN_P * error - TURN_D * angvel).clamp(-1.0, 1.0)
+}
+
+impl Simulation {
+    /// Pilot's input with the allowed assists added to it
+    pub(super) fn assisted_input(&self, input: &ShipInput) -> ShipInput {
+        let assists = input.assists.allowed(&self.ruleset.assists);
+        let ship = match &self.ship {
+            Some(ship) if assists.any() => ship,
+            _ => return *input,
+        };
+        let body = &self.rigid_body_set[ship.body];
+        let position = *body.translation();
+        let mut assisted = *input;
+
+        // Rotation (only when the pilot doesn't rotate)
+        if input.rotate_left == 0.0 && input.rotate_right == 0.0 {
+            let angle = body.rotation().angle();
+            let rotation = match assists
+                .orientation
+                .and_then(|o| self.orient_direction(o, position))
+            {
+                Some(direction) => turn_toward(angle, body.angvel(), direction),
+                None if assists.rotation_damping => {
+                    (-body.angvel() * DAMPING_GAIN).clamp(-1.0, 1.0)
+                }
+                None => 0.0,
+            };
+            assisted.rotate_left = (-rotation).max(0.0);
+            assisted.rotate_right = rotation.max(0.0);
+        }
+
+        // Thrust countering gravity, added to pilot's
+        if assists.hold_velocity {
+            let force = -gravity_at(&self.gravity_sources(), position) * body.mass();
+            let local = body.rotation().inverse() * force;
+            let stats = &ship.stats;
+            // Nose is -Y, so the main engine pushes toward -Y
+            assisted.forward += (-local.y / stats.main_thrust).max(0.0);
+            assisted.backward += (local.y / stats.side_thrust).max(0.0);
+            assisted.left += (-local.x / stats.side_thrust).max(0.0);
+            assisted.right += (local.x / stats.side_thrust).max(0.0);
+            assisted.forward = assisted.forward.min(1.0);
+            assisted.backward = assisted.backward.min(1.0);
+            assisted.left = assisted.left.min(1.0);
+            assisted.right = assisted.right.min(1.0);
+        }
+
+        assisted.assists = assists;
+        assisted
+    }
+
+    /// Direction (in world space) the ship should point to
+    fn orient_direction(&self, orientation: Orientation, position: NVector2) -> Option<NVector2> {
+        let ship = self.ship.as_ref()?;
+        match orientation {
+            Orientation::NextGate => {
+                let gate = self.gates.get(self.next_gate as usize)?;
+                Some(self.rigid_body_set[gate.body].translation() - position)
+            }
+            Orientation::Prograde | Orientation::Retrograde => {
+                let distance = |body: &RigidBody| (body.translation() - position).norm_squared();
+                let planet_velocity = self
+                    .planets
+                    .iter()
+                    .map(|planet| &self.rigid_body_set[planet.body])
+                    .min_by(|a, b| {
+                        distance(a)
+                            .partial_cmp(&distance(b))
+                            .unwrap_or(Ordering::Equal)
+                    })
+                    .map(|planet| *planet.linvel())
+                    .unwrap_or_else(NVector2::zeros);
+                let velocity = self.rigid_body_set[ship.body].linvel() - planet_velocity;
+                if orientation == Orientation::Prograde {
+                    Some(velocity)
+                } else {
+                    Some(-velocity)
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,267 @@
+use super::{CollisionGroup, Simulation, WithGroup};
+
+use crate::math::{polygon, NPoint2, NVector2};
+
+use rapier2d::prelude::*;
+
+use rand::prelude::*;
+
+use std::f32::consts::PI;
+
+/// Asteroids smaller than this just vanish instead of breaking apart
+const MIN_FRACTURE_AREA: f32 = 450.0;
+
+/// How many despawned asteroids are kept around for reuse
+const ASTEROID_POOL_SIZE: usize = 100;
+
+/// View radius is rounded up to this (so zooming a bit doesn't change it)
+const VIEW_RADIUS_STEP: f32 = 250.0;
+/// Asteroids further than this many view radii from the ship get despawned
+const DESPAWN_VIEW_RADII: f32 = 2.5;
+
+impl Simulation {
+    pub fn is_asteroid(&self, col: ColliderHandle) -> bool {
+        self.asteroids.iter().any(|(c, _)| *c == col)
+    }
+
+    pub fn view_radius(&self) -> f32 {
+        self.view_radius
+    }
+
+    /// Sets how far the frontend sees from the ship (half the diagonal of its view),
+    /// so asteroids never appear or vanish in sight. Replays record every change
+    pub fn set_view_radius(&mut self, radius: f32) {
+        self.view_radius = (radius / VIEW_RADIUS_STEP).ceil() * VIEW_RADIUS_STEP;
+    }
+
+    /// Breaks an asteroid into smaller pieces (or just removes it if it's small)
+    pub fn remove_asteroid(&mut self, col: ColliderHandle) {
+        let shape = self.collider_set[col].shape().as_convex_polygon();
+        let polygon = shape.map(|p| p.points().to_vec()).unwrap_or_default();
+        let rigid_body = &self.rigid_body_set[self.collider_set[col].parent().unwrap()];
+        let position = *rigid_body.position();
+        let linvel = *rigid_body.linvel();
+        let angvel = rigid_body.angvel();
+        if polygon::area(&polygon) > MIN_FRACTURE_AREA {
+            // Break it into pieces that together make up the whole asteroid
+            let piece_count = self.rng.gen_range(2..4);
+            for piece in polygon::fracture(&mut self.rng, &polygon, piece_count) {
+                let center = polygon::centroid(&piece);
+                let piece = polygon::translated(&piece, -center.coords);
+                // Piece keeps the velocity the asteroid had at its center
+                // and gets pushed away from the center a bit
+                let offset = position.rotation * center.coords;
+                let spin_vel = vector![-offset.y, offset.x] * angvel;
+                let push = offset.try_normalize(0.01).unwrap_or_else(NVector2::zeros)
+                    * self.rng.gen_range(20.0..60.0);
+                let velocities = RigidBodyVelocity {
+                    linvel: linvel + spin_vel + push,
+                    angvel: angvel * self.rng.gen_range(0.8..1.2),
+                };
+                self.spawn_asteroid_polygon(
+                    position.translation.vector + offset,
+                    position.rotation.angle(),
+                    piece,
+                    velocities,
+                );
+            }
+        }
+        self.despawn_asteroid(col);
+    }
+
+    /// Takes an asteroid out of the simulation, keeping it for reuse if the pool isn't full
+    pub fn despawn_asteroid(&mut self, col: ColliderHandle) {
+        let index = match self.asteroids.iter().position(|(c, _)| *c == col) {
+            Some(index) => index,
+            None => return,
+        };
+        let (col, body_handle) = self.asteroids.remove(index);
+        self.belt_asteroids.retain(|c| *c != col);
+
+        if self.asteroid_pool.len() >= ASTEROID_POOL_SIZE {
+            self.remove_rigidbody(body_handle);
+            return;
+        }
+
+        // Park it where it is: static and not colliding with anything
+        let body = &mut self.rigid_body_set[body_handle];
+        body.set_linvel(vector![0., 0.], false);
+        body.set_angvel(0., false);
+        body.set_body_type(RigidBodyType::Static);
+        self.collider_set[col].set_collision_groups(InteractionGroups::none());
+        self.asteroid_pool.push((col, body_handle));
+    }
+
+    /// Brings a pooled asteroid back at given position (it keeps its shape)
+    fn respawn_asteroid(
+        &mut self,
+        col: ColliderHandle,
+        body_handle: RigidBodyHandle,
+        position: NVector2,
+        velocities: RigidBodyVelocity,
+    ) {
+        let body = &mut self.rigid_body_set[body_handle];
+        body.set_body_type(RigidBodyType::Dynamic);
+        body.set_position(Isometry::new(position, position.x * position.y), true);
+        body.set_linvel(velocities.linvel, true);
+        body.set_angvel(velocities.angvel, true);
+        self.collider_set[col].set_collision_groups(
+            self.ruleset
+                .collisions
+                .collision_groups(CollisionGroup::Asteroid),
+        );
+        self.asteroids.push((col, body_handle));
+    }
+
+    /// Despawns asteroids that are too far from `center`
+    /// and spawns new ones around it (just outside of view)
+    pub(super) fn update_asteroids(&mut self, center: NVector2) {
+        let spawn_r = self.ruleset.asteroid_spawn_radius.max(self.view_radius);
+        let despawn_r = self
+            .ruleset
+            .asteroid_despawn_radius
+            .max(self.view_radius * DESPAWN_VIEW_RADII);
+        let far_asteroids: Vec<ColliderHandle> = self
+            .asteroids
+            .iter()
+            .filter(|(col, _)| {
+                let dist = (self.collider_set[*col].translation() - center).norm();
+                dist > despawn_r && !self.belt_asteroids.contains(col)
+            })
+            .map(|(col, _)| *col)
+            .collect();
+        for col in far_asteroids {
+            self.despawn_asteroid(col);
+        }
+
+        // Belts don't count toward the budget
+        let flying = self.asteroids.len() - self.belt_asteroids.len();
+        if self.asteroid_spawn_timer > self.ruleset.asteroid_spawn_interval
+            && flying < self.ruleset.asteroid_budget
+        {
+            let r = spawn_r * (1.0 + self.rng.gen::<f32>());
+            let offset = Rotation::new(self.rng.gen::<f32>() * 2. * PI) * vector![0., 1.] * r;
+            let pos = center + offset;
+            let linvel = self.rng.gen_range(30.0..300.0)
+                * vector![
+                    self.rng.gen_range(-1.0..1.0f32),
+                    self.rng.gen_range(-1.0..1.0f32)
+                ]
+                .normalize();
+            let angvel = self.rng.gen_range(-10.0..10.0);
+            let velocities = RigidBodyVelocity { linvel, angvel };
+            if let Some((col, body)) = self.asteroid_pool.pop() {
+                self.respawn_asteroid(col, body, pos, velocities);
+            } else {
+                let scale = self.rng.gen_range(0.2..0.6);
+                self.spawn_asteroid(pos, scale, velocities);
+            }
+            self.asteroid_spawn_timer = 0.;
+        }
+    }
+
+    /// Spawns a random asteroid at given position
+    pub fn spawn_asteroid(
+        &mut self,
+        position: NVector2,
+        scale: f32,
+        velocities: RigidBodyVelocity,
+    ) {
+        let polygon = polygon::random_convex(&mut self.rng, 40.0 * scale);
+        self.spawn_asteroid_polygon(position, position.x * position.y, polygon, velocities);
+    }
+
+    /// Spawns an asteroid with given shape (in local space, centered on its centroid)
+    pub fn spawn_asteroid_polygon(
+        &mut self,
+        position: NVector2,
+        rotation: f32,
+        polygon: Vec<NPoint2>,
+        velocities: RigidBodyVelocity,
+    ) {
+        let collider = match ColliderBuilder::convex_polygon(polygon) {
+            Some(builder) => builder
+                .group(CollisionGroup::Asteroid, &self.ruleset.collisions)
+                .restitution(0.8)
+                .density(2.0)
+                .active_events(ActiveEvents::CONTACT_EVENTS)
+                .build(),
+            // Degenerate shape
+            None => return,
+        };
+
+        let rigid_body = RigidBodyBuilder::new_dynamic()
+            .translation(position)
+            .rotation(rotation)
+            .linvel(velocities.linvel)
+            .angvel(velocities.angvel)
+            .can_sleep(false)
+            .build();
+
+        let rigid_body_handle = self.rigid_body_set.insert(rigid_body);
+
+        let col_handle = self.collider_set.insert_with_parent(
+            collider,
+            rigid_body_handle,
+            &mut self.rigid_body_set,
+        );
+        self.asteroids.push((col_handle, rigid_body_handle));
+    }
+
+    /// Spawns a belt of asteroids around given planet
+    pub fn spawn_asteroids_around_planet(&mut self, planet_pos: NVector2, planet_radius: f32) {
+        let asteroid_count = self.rng.gen_range(10..30);
+        let belt_start = self.asteroids.len();
+        for _ in 0..asteroid_count {
+            let rot = Rotation::new(self.rng.gen_range(0.0..2.0 * PI));
+            let offset = rot * vector![1., 0.] * self.rng.gen_range(1.5..5.5) * planet_radius;
+            let linvel = self.rng.gen_range(30.0..300.0)
+                * vector![
+                    self.rng.gen_range(-1.0..1.0f32),
+                    self.rng.gen_range(-1.0..1.0f32)
+                ]
+                .normalize();
+            let angvel = self.rng.gen_range(-10.0..10.0);
+            let scale = self.rng.gen_range(0.2..0.6);
+            self.spawn_asteroid(
+                planet_pos + offset,
+                scale,
+                RigidBodyVelocity { linvel, angvel },
+            );
+        }
+        let spawned = &self.asteroids[belt_start..];
+        self.belt_asteroids
+            .extend(spawned.iter().map(|(col, _)| *col));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::ruleset::Ruleset;
+    use crate::simulation::ShipStats;
+
+    #[test]
+    fn belts_stay_when_the_ship_flies_away() {
+        let mut sim = Simulation::new_level(5, Ruleset::classic(), 3, ShipStats::default());
+        let belt = sim.belt_asteroids.clone();
+        assert!(!belt.is_empty());
+        let still = RigidBodyVelocity {
+            linvel: vector![0., 0.],
+            angvel: 0.,
+        };
+        sim.spawn_asteroid(vector![0., 0.], 0.4, still);
+        let (flying, _) = *sim.asteroids.last().unwrap();
+
+        let far_away = vector![1.0e6, 1.0e6];
+        for _ in 0..10 {
+            sim.update_asteroids(far_away);
+        }
+        assert!(!sim.is_asteroid(flying), "far asteroid should be despawned");
+        sim.update_asteroids(vector![0., 0.]);
+
+        assert_eq!(sim.belt_asteroids, belt);
+        assert!(belt.iter().all(|col| sim.is_asteroid(*col)));
+    }
+}
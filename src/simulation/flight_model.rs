@@ -0,0 +1,76 @@
+use rapier2d::prelude::*;
+
+use serde::{Deserialize, Serialize};
+
+/// How a ship moves besides thrust and gravity
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum FlightModel {
+    /// Keeps drifting, only spinning is limited
+    Newtonian,
+    /// Spinning slows down by itself, drifting doesn't
+    Assisted,
+    /// Drifting and spinning both slow down and speed is limited
+    Arcade,
+    Custom(FlightParams),
+}
+
+impl Default for FlightModel {
+    fn default() -> Self {
+        FlightModel::Newtonian
+    }
+}
+
+impl FlightModel {
+    pub fn params(&self) -> FlightParams {
+        match self {
+            FlightModel::Newtonian => FlightParams {
+                linear_damping: 0.0,
+                angular_damping: 0.0,
+                max_angvel: Some(3.0),
+                max_speed: None,
+            },
+            FlightModel::Assisted => FlightParams {
+                linear_damping: 0.0,
+                angular_damping: 1.5,
+                max_angvel: Some(3.0),
+                max_speed: None,
+            },
+            FlightModel::Arcade => FlightParams {
+                linear_damping: 0.4,
+                angular_damping: 3.0,
+                max_angvel: Some(2.5),
+                max_speed: Some(1500.0),
+            },
+            FlightModel::Custom(params) => *params,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct FlightParams {
+    /// Damping is given to the body when it's built (rapier applies it every step)
+    pub linear_damping: f32,
+    pub angular_damping: f32,
+    /// Faster spinning (either way) gets slowed down (in rad/s)
+    pub max_angvel: Option<f32>,
+    /// Faster movement gets slowed down
+    pub max_speed: Option<f32>,
+}
+
+impl FlightParams {
+    /// Slows the body down softly if it's over the limits
+    pub fn limit(&self, delta: f32, body: &mut RigidBody) {
+        if let Some(max_angvel) = self.max_angvel {
+            let angvel = body.angvel();
+            if angvel.abs() > max_angvel {
+                body.set_angvel(angvel / (1.0 + delta), true);
+            }
+        }
+        if let Some(max_speed) = self.max_speed {
+            let linvel = *body.linvel();
+            if linvel.norm() > max_speed {
+                body.set_linvel(linvel / (1.0 + delta * 4.0), true);
+            }
+        }
+    }
+}
@@ -0,0 +1,250 @@
+//! Ways to get fuel back in fuel mode: canisters along the course,
+//! slow orbits around marked planets and docking at stations
+
+use super::{CollisionGroup, Simulation, WithGroup};
+
+use crate::math::NVector2;
+
+use rapier2d::prelude::*;
+
+use rand::prelude::*;
+
+use serde::{Deserialize, Serialize};
+
+/// Part of the level's fuel budget the ship starts with, canisters hold the rest
+const START_SHARE: f32 = 0.6;
+/// Radius of a canister's sensor (the ship picks it up when it touches it)
+const CANISTER_PICKUP_RADIUS: f32 = 70.0;
+/// One station for this many gates
+const GATES_PER_STATION: u16 = 8;
+/// Ship docks with a station when it touches a sensor this big...
+pub const STATION_DOCK_RADIUS: f32 = 130.0;
+/// ...if it's slower than this
+const DOCK_MAX_SPEED: f32 = 60.0;
+/// Slow orbits around marked planets are between these altitudes (in planet radii)
+pub const ORBIT_MIN_ALTITUDE: f32 = 0.15;
+pub const ORBIT_MAX_ALTITUDE: f32 = 1.0;
+/// Orbits faster than this don't count as slow
+const ORBIT_MAX_SPEED: f32 = 450.0;
+/// Fuel per second (in parts of the ship's per-gate capacity)
+const STATION_REFUEL_RATE: f32 = 0.5;
+const ORBIT_REFUEL_RATE: f32 = 0.2;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct FuelCanister {
+    pub position: NVector2,
+    pub amount: f32,
+    pub taken: bool,
+    /// Sensor in the pickup group
+    collider: ColliderHandle,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Station {
+    pub position: NVector2,
+    /// Sensor in the pickup group
+    collider: ColliderHandle,
+}
+
+/// Where the ship is getting fuel from right now
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Refuelling {
+    Station,
+    /// Parked on a landing pad
+    Pad,
+    Orbit,
+}
+
+impl Simulation {
+    /// Places canisters and stations along the course and marks refuelling planets
+    /// (after gates and the ship are spawned). `budget` is all the fuel the level needs
+    pub fn spawn_fuel_sources(&mut self, budget: f32) {
+        if let Some(ship) = self.ship.as_mut() {
+            ship.tank = budget;
+            ship.fuel = budget * START_SHARE;
+        }
+
+        let gate_positions: Vec<NVector2> = self
+            .gates
+            .iter()
+            .map(|gate| *self.rigid_body_set[gate.body].translation())
+            .collect();
+        let legs: Vec<(NVector2, NVector2)> = gate_positions
+            .iter()
+            .zip(gate_positions.iter().skip(1))
+            .map(|(a, b)| (*a, *b))
+            .collect();
+
+        // Canisters on every other leg between gates
+        let canister_legs: Vec<usize> = (0..legs.len()).step_by(2).collect();
+        if !canister_legs.is_empty() {
+            let amount = budget * (1.0 - START_SHARE) / canister_legs.len() as f32;
+            for leg in canister_legs {
+                let (a, b) = legs[leg];
+                let along = self.rng.gen_range(0.3..0.7);
+                let offset = vector![
+                    self.rng.gen_range(-150.0..150.0),
+                    self.rng.gen_range(-150.0..150.0)
+                ];
+                let position = a + (b - a) * along + offset;
+                if self.clear_of_planets(position, CANISTER_PICKUP_RADIUS) {
+                    let collider = self.spawn_pickup_sensor(position, CANISTER_PICKUP_RADIUS);
+                    self.canisters.push(FuelCanister {
+                        position,
+                        amount,
+                        taken: false,
+                        collider,
+                    });
+                }
+            }
+        }
+
+        // Stations beside the course
+        let station_count = self.gate_count as u16 / GATES_PER_STATION;
+        for i in 0..station_count {
+            let leg = ((i + 1) * GATES_PER_STATION) as usize - 1;
+            let (a, b) = match legs.get(leg) {
+                Some(leg) => *leg,
+                None => break,
+            };
+            let side = (b - a).try_normalize(0.01).unwrap_or_else(NVector2::zeros);
+            let position = (a + b) * 0.5 + vector![-side.y, side.x] * 300.0;
+            if self.clear_of_planets(position, STATION_DOCK_RADIUS) {
+                let collider = self.spawn_pickup_sensor(position, STATION_DOCK_RADIUS);
+                self.stations.push(Station { position, collider });
+            }
+        }
+
+        // Some planets with gates are good for refuelling
+        let gate_planets: Vec<usize> = (0..self.planets.len())
+            .filter(|i| {
+                let planet = &self.planets[*i];
+                let center = self.rigid_body_set[planet.body].translation();
+                // Gates are at most 2.2 radii (plus a bit) from their planet
+                gate_positions
+                    .iter()
+                    .any(|gate| (gate - center).norm() < planet.radius * 2.3 + 100.0)
+            })
+            .collect();
+        for i in gate_planets {
+            if self.rng.gen_bool(0.35) {
+                self.planets[i].refuel = true;
+            }
+        }
+    }
+
+    /// Round sensor (not attached to any body) that only the ship notices
+    fn spawn_pickup_sensor(&mut self, position: NVector2, radius: f32) -> ColliderHandle {
+        let collider = ColliderBuilder::ball(radius)
+            .translation(position)
+            .sensor(true)
+            .group(CollisionGroup::Pickup, &self.ruleset.collisions)
+            .build();
+        self.collider_set.insert(collider)
+    }
+
+    /// Nothing within `margin` of `position` is inside a planet
+    fn clear_of_planets(&self, position: NVector2, margin: f32) -> bool {
+        self.planets.iter().all(|planet| {
+            let center = self.rigid_body_set[planet.body].translation();
+            (position - center).norm() > planet.radius + margin
+        })
+    }
+
+    /// Canisters that haven't been picked up yet
+    pub fn canisters(&self) -> impl Iterator<Item = &FuelCanister> {
+        self.canisters.iter().filter(|canister| !canister.taken)
+    }
+
+    pub fn stations(&self) -> &[Station] {
+        &self.stations
+    }
+
+    /// Where the ship is refuelling at its current position and velocity
+    pub fn refuelling(&self) -> Option<Refuelling> {
+        let ship = self.ship.as_ref()?;
+        let body = &self.rigid_body_set[ship.body];
+        let position = body.translation();
+        let speed = body.linvel().norm();
+
+        if ship.landed.is_some() {
+            return Some(Refuelling::Pad);
+        }
+
+        let touches = |collider| self.physics_server.intersecting(ship.collider, collider);
+        let docked = self
+            .stations
+            .iter()
+            .any(|station| touches(station.collider));
+        if docked && speed < DOCK_MAX_SPEED {
+            return Some(Refuelling::Station);
+        }
+
+        let orbiting = self
+            .planets
+            .iter()
+            .filter(|planet| planet.refuel)
+            .any(|planet| {
+                let center = self.rigid_body_set[planet.body].translation();
+                let altitude = (position - center).norm() - planet.radius;
+                altitude > planet.radius * ORBIT_MIN_ALTITUDE
+                    && altitude < planet.radius * ORBIT_MAX_ALTITUDE
+            });
+        if orbiting && speed < ORBIT_MAX_SPEED {
+            return Some(Refuelling::Orbit);
+        }
+        None
+    }
+
+    /// Picks up canisters the ship touches and refuels it at stations and planets
+    pub(super) fn collect_fuel(&mut self, delta: f32) {
+        let refuelling = self.refuelling();
+        let ship = match self.ship.as_mut() {
+            Some(ship) => ship,
+            None => return,
+        };
+        let fuel_before = ship.fuel;
+
+        for canister in self.canisters.iter_mut().filter(|c| !c.taken) {
+            if self
+                .physics_server
+                .intersecting(ship.collider, canister.collider)
+            {
+                canister.taken = true;
+                ship.fuel += canister.amount;
+                // Nothing left to touch
+                self.collider_set[canister.collider]
+                    .set_collision_groups(InteractionGroups::none());
+            }
+        }
+        let rate = match refuelling {
+            Some(Refuelling::Station) | Some(Refuelling::Pad) => STATION_REFUEL_RATE,
+            Some(Refuelling::Orbit) => ORBIT_REFUEL_RATE,
+            None => 0.0,
+        };
+        ship.fuel += rate * ship.stats.fuel_capacity * delta;
+        // Stations and pads fill up the RCS tank too
+        if matches!(
+            refuelling,
+            Some(Refuelling::Station) | Some(Refuelling::Pad)
+        ) {
+            let rcs = ship.rcs + rate * ship.stats.rcs_capacity * delta;
+            ship.rcs = rcs.min(ship.stats.rcs_capacity);
+        }
+
+        ship.fuel = ship.fuel.min(ship.tank);
+        self.stats.fuel_collected += (ship.fuel - fuel_before).max(0.0);
+    }
+
+    /// Roughly how many more gates the fuel left is good for,
+    /// judging by the fuel used per gate so far
+    pub fn fuel_range(&self) -> Option<f32> {
+        let ship = self.ship.as_ref()?;
+        let fuel_used = self.stats.fuel_used.engines();
+        if self.next_gate == 0 || fuel_used <= 0.0 {
+            return None;
+        }
+        let per_gate = fuel_used / self.next_gate as f32;
+        Some(ship.fuel / per_gate)
+    }
+}
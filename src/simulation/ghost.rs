@@ -0,0 +1,168 @@
+use super::{Simulation, TIMESTEP};
+
+use crate::math::NVector2;
+use crate::ruleset::Ruleset;
+
+use serde::{Deserialize, Serialize};
+
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+/// Personal bests are saved here, one file for each course
+const GHOSTS_DIR: &str = "ghosts";
+/// Steps between two recorded frames
+const FRAME_INTERVAL: u32 = 3;
+
+/// Ship's position at one moment of a run
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct GhostFrame {
+    pub position: (f32, f32),
+    pub rotation: f32,
+}
+
+/// Level a ghost was flown on, ghosts only race on the same one
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+struct Course {
+    seed: u64,
+    gate_count: u32,
+    ruleset: Ruleset,
+}
+
+impl Course {
+    fn of(sim: &Simulation) -> Self {
+        Course {
+            seed: sim.seed(),
+            gate_count: sim.gate_count(),
+            ruleset: *sim.ruleset(),
+        }
+    }
+}
+
+/// Recorded trajectory of a run with the time each gate was passed.
+/// Recording follows the simulation's clock, so rewinding or loading
+/// overwrites the part of the run that got undone
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Ghost {
+    course: Course,
+    /// Frames every `FRAME_INTERVAL` steps from the start
+    frames: Vec<GhostFrame>,
+    /// Time each gate was passed at
+    splits: Vec<f32>,
+    /// Time of the whole run (`None` until it's finished)
+    time: Option<f32>,
+}
+
+impl Ghost {
+    /// Empty recording for the course `sim` is on
+    pub fn new(sim: &Simulation) -> Self {
+        Ghost {
+            course: Course::of(sim),
+            frames: Vec::new(),
+            splits: Vec::new(),
+            time: None,
+        }
+    }
+
+    /// Ghost can race on the level `sim` is on
+    pub fn fits(&self, sim: &Simulation) -> bool {
+        self.course == Course::of(sim)
+    }
+
+    pub fn time(&self) -> Option<f32> {
+        self.time
+    }
+
+    /// Time the ghost passed given gate at
+    pub fn split(&self, gate: u32) -> Option<f32> {
+        self.splits.get(gate as usize).copied()
+    }
+
+    /// Has to be called after every simulation step
+    pub fn record(&mut self, sim: &Simulation) {
+        if self.time.is_some() {
+            return;
+        }
+        let time = sim.time_since_start();
+        let frame = (time / (TIMESTEP * FRAME_INTERVAL as f32)).round() as usize;
+        self.frames.truncate(frame);
+        if let Some(ship) = sim.ship() {
+            let body = &sim.bodies()[ship.body];
+            let current = GhostFrame {
+                position: (body.translation().x, body.translation().y),
+                rotation: body.rotation().angle(),
+            };
+            // Fills gaps too (e.g. after loading a later quicksave)
+            while self.frames.len() <= frame {
+                self.frames.push(current);
+            }
+        }
+
+        let passed = sim.next_gate() as usize;
+        self.splits.truncate(passed);
+        while self.splits.len() < passed {
+            self.splits.push(time);
+        }
+
+        if sim.is_completed() && !sim.is_failed() {
+            self.time = Some(time);
+        }
+    }
+
+    /// Where the ghost was at `time` (it stays at the end after finishing)
+    pub fn frame_at(&self, time: f32) -> Option<GhostFrame> {
+        let frame_time = TIMESTEP * FRAME_INTERVAL as f32;
+        let index = (time / frame_time).max(0.0);
+        let last = self.frames.len().checked_sub(1)?;
+        let (a, b) = (index.floor() as usize, index.ceil() as usize);
+        if a >= last {
+            return self.frames.get(last).copied();
+        }
+        let (from, to) = (self.frames[a], self.frames[b]);
+        let s = index.fract();
+        let from_position = NVector2::new(from.position.0, from.position.1);
+        let to_position = NVector2::new(to.position.0, to.position.1);
+        let position = from_position + (to_position - from_position) * s;
+        // Shortest way around
+        let turn = (to.rotation - from.rotation + std::f32::consts::PI)
+            .rem_euclid(std::f32::consts::TAU)
+            - std::f32::consts::PI;
+        Some(GhostFrame {
+            position: (position.x, position.y),
+            rotation: from.rotation + turn * s,
+        })
+    }
+
+    /// Finished faster than `other` (or `other` isn't finished)
+    pub fn beats(&self, other: Option<&Ghost>) -> bool {
+        match (self.time, other.and_then(|other| other.time)) {
+            (Some(time), Some(best)) => time < best,
+            (Some(_), None) => true,
+            (None, _) => false,
+        }
+    }
+
+    /// File of the personal best for the course `sim` is on
+    pub fn best_path(sim: &Simulation) -> String {
+        format!(
+            "{}/{}-{}-{}.ghost",
+            GHOSTS_DIR,
+            sim.seed(),
+            sim.gate_count(),
+            sim.ruleset().name().to_lowercase()
+        )
+    }
+
+    pub fn save(&self, path: &str) -> bincode::Result<()> {
+        if let Some(dir) = Path::new(path).parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let writer = BufWriter::new(File::create(path)?);
+        bincode::serialize_into(writer, self)
+    }
+
+    pub fn load(path: &str) -> bincode::Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        bincode::deserialize_from(reader)
+    }
+}
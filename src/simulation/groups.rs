@@ -0,0 +1,120 @@
+//! Collision groups of colliders and which groups they interact with.
+//! Pairs that aren't in each other's interaction masks are never tested by rapier,
+//! pairs that aren't in each other's solver masks touch (and report it) but don't push each other.
+//! Masks are part of the ruleset, so a ruleset can change what hits what.
+
+use rapier2d::prelude::*;
+
+use serde::{Deserialize, Serialize};
+
+const PLAYER: u32 = 1 << 0;
+const ASTEROID: u32 = 1 << 1;
+const PLANET: u32 = 1 << 2;
+const GATE_SOLID: u32 = 1 << 3;
+const GATE_SENSOR: u32 = 1 << 4;
+const PICKUP: u32 = 1 << 5;
+const PROJECTILE: u32 = 1 << 6;
+
+const GROUP_COUNT: usize = 7;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CollisionGroup {
+    Player,
+    Asteroid,
+    Planet,
+    /// Posts of gates
+    GateSolid,
+    /// Area between posts of a gate
+    GateSensor,
+    /// Fuel canisters and stations (sensors the ship flies into)
+    Pickup,
+    Projectile,
+}
+
+impl CollisionGroup {
+    fn bit(self) -> u32 {
+        match self {
+            CollisionGroup::Player => PLAYER,
+            CollisionGroup::Asteroid => ASTEROID,
+            CollisionGroup::Planet => PLANET,
+            CollisionGroup::GateSolid => GATE_SOLID,
+            CollisionGroup::GateSensor => GATE_SENSOR,
+            CollisionGroup::Pickup => PICKUP,
+            CollisionGroup::Projectile => PROJECTILE,
+        }
+    }
+
+    /// Index into the masks of `CollisionMasks`
+    fn index(self) -> usize {
+        self.bit().trailing_zeros() as usize
+    }
+}
+
+/// Interaction and solver masks of every group
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct CollisionMasks {
+    /// Groups each group is tested against (both sides have to agree)
+    interaction: [u32; GROUP_COUNT],
+    /// Groups each group exchanges forces with (both sides have to agree)
+    solver: [u32; GROUP_COUNT],
+}
+
+impl Default for CollisionMasks {
+    fn default() -> Self {
+        CollisionMasks {
+            // In `CollisionGroup` order
+            interaction: [
+                ASTEROID | PLANET | GATE_SOLID | GATE_SENSOR | PICKUP,
+                // Asteroids don't hit each other, they'd only clump up
+                PLAYER | PLANET | GATE_SOLID | PROJECTILE,
+                PLAYER | ASTEROID | PROJECTILE,
+                PLAYER | ASTEROID | PROJECTILE,
+                // Sensors only care about the ship
+                PLAYER,
+                PLAYER,
+                ASTEROID | PLANET | GATE_SOLID,
+            ],
+            solver: [
+                ASTEROID | PLANET | GATE_SOLID,
+                PLAYER | PLANET | GATE_SOLID | PROJECTILE,
+                PLAYER | ASTEROID,
+                PLAYER | ASTEROID,
+                0,
+                0,
+                // Shots knock asteroids a bit (they're removed right after)
+                ASTEROID,
+            ],
+        }
+    }
+}
+
+impl CollisionMasks {
+    /// Same masks, except `a` and `b` pass through each other without noticing
+    pub fn ignoring(mut self, a: CollisionGroup, b: CollisionGroup) -> Self {
+        for (group, other) in [(a, b), (b, a)] {
+            self.interaction[group.index()] &= !other.bit();
+            self.solver[group.index()] &= !other.bit();
+        }
+        self
+    }
+
+    pub fn collision_groups(&self, group: CollisionGroup) -> InteractionGroups {
+        InteractionGroups::new(group.bit(), self.interaction[group.index()])
+    }
+
+    pub fn solver_groups(&self, group: CollisionGroup) -> InteractionGroups {
+        InteractionGroups::new(group.bit(), self.solver[group.index()])
+    }
+}
+
+/// Adds collision and solver groups of `group` to a collider
+pub trait WithGroup {
+    fn group(self, group: CollisionGroup, masks: &CollisionMasks) -> Self;
+}
+
+impl WithGroup for ColliderBuilder {
+    fn group(self, group: CollisionGroup, masks: &CollisionMasks) -> Self {
+        self.collision_groups(masks.collision_groups(group))
+            .solver_groups(masks.solver_groups(group))
+    }
+}
@@ -0,0 +1,59 @@
+//! Ship heat: the afterburner heats the ship up, space around it cools it down
+
+use super::Simulation;
+
+use crate::math::NVector2;
+
+/// Direction light comes from (angle in world space, 0 is +X)
+pub const SUN_ANGLE: f32 = -0.6;
+
+/// Heat (0.0 is cold, 1.0 overheated) the afterburner adds per second
+pub const BOOST_HEAT: f32 = 0.3;
+/// Afterburner multiplies main thrust (and its fuel use) by this
+pub const BOOST_MULTIPLIER: f32 = 2.5;
+/// Seconds thrusters stay off after overheating
+pub const OVERHEAT_COOLDOWN: f32 = 3.0;
+
+/// Heat lost per second in open space
+const COOLING: f32 = 0.12;
+/// Cooling is this many times faster in a planet's shadow
+const SHADE_COOLING: f32 = 2.5;
+/// Planets warm ships up to this many radii above their surface
+const PLANET_HEAT_RANGE: f32 = 2.0;
+/// Part of cooling a planet takes away right at its surface
+const PLANET_HEAT: f32 = 0.8;
+
+/// Unit vector pointing toward the sun
+fn sun_direction() -> NVector2 {
+    NVector2::new(SUN_ANGLE.cos(), SUN_ANGLE.sin())
+}
+
+impl Simulation {
+    /// Heat lost per second at given position
+    pub fn cooling_rate(&self, position: NVector2) -> f32 {
+        let mut rate = COOLING;
+        for planet in self.planets.iter() {
+            let to_planet = self.rigid_body_set[planet.body].translation() - position;
+            let distance = to_planet.norm();
+
+            // Closer to the surface, less cooling
+            let altitude = (distance - planet.radius).max(0.0);
+            let closeness = 1.0 - (altitude / (planet.radius * PLANET_HEAT_RANGE)).min(1.0);
+            rate *= 1.0 - closeness * PLANET_HEAT;
+        }
+        if self.is_shaded(position) {
+            rate *= SHADE_COOLING;
+        }
+        rate
+    }
+
+    /// A planet is between `position` and the sun
+    pub fn is_shaded(&self, position: NVector2) -> bool {
+        let sun = sun_direction();
+        self.planets.iter().any(|planet| {
+            let to_planet = self.rigid_body_set[planet.body].translation() - position;
+            let along = to_planet.dot(&sun);
+            along > 0.0 && (to_planet - sun * along).norm() < planet.radius
+        })
+    }
+}
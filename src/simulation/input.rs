@@ -0,0 +1,25 @@
+use super::Assists;
+
+use serde::{Deserialize, Serialize};
+
+/// What the pilot does during one simulation step.
+/// Every value is in 0.0..=1.0 (keyboard gives either 0.0 or 1.0)
+#[derive(Clone, Copy, Default, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ShipInput {
+    pub forward: f32,
+    pub backward: f32,
+    pub left: f32,
+    pub right: f32,
+    pub rotate_left: f32,
+    pub rotate_right: f32,
+    /// Afterburner (only boosts the main engine)
+    pub boost: bool,
+    /// Grappling tether (attached while held)
+    pub tether: bool,
+    /// Gun (fires repeatedly while held, if the ruleset has weapons)
+    pub fire: bool,
+    /// Deflector shield (raised when pressed, if it's charged)
+    pub shield: bool,
+    /// Flight assists the pilot has switched on
+    pub assists: Assists,
+}
@@ -0,0 +1,149 @@
+//! Landing pads on planets. Coming down on one slowly and upright parks the ship
+//! (no points lost), thrusting forward takes off again
+
+use super::{ShipInput, SimEvent, Simulation};
+
+use crate::math::NVector2;
+
+use rapier2d::prelude::*;
+
+use rand::prelude::*;
+
+use serde::{Deserialize, Serialize};
+
+/// Chance of a planet getting a pad
+const PAD_CHANCE: f64 = 0.5;
+/// Half of the width of a pad (along the surface)
+pub const PAD_HALF_WIDTH: f32 = 70.0;
+/// Faster touchdowns are crashes
+const LANDING_MAX_SPEED: f32 = 90.0;
+/// Ship can lean at most this much from the surface normal (cosine of the angle)
+const LANDING_MIN_UPRIGHT: f32 = 0.9;
+/// Points for the first landing on each pad
+const LANDING_POINTS: i32 = 15;
+/// Parked ship floats this high above the surface (so it doesn't keep touching it)
+const PARK_HEIGHT: f32 = 4.0;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct LandingPad {
+    /// Index into planets
+    pub planet: usize,
+    /// Where on the surface (angle from the planet's center)
+    pub angle: f32,
+    /// Ship has landed here already
+    pub visited: bool,
+}
+
+impl Simulation {
+    /// Puts pads on random planets (after planets are spawned)
+    pub fn spawn_landing_pads(&mut self) {
+        for planet in 0..self.planets.len() {
+            if self.rng.gen_bool(PAD_CHANCE) {
+                let angle = self.rng.gen_range(0.0..std::f32::consts::TAU);
+                self.landing_pads.push(LandingPad {
+                    planet,
+                    angle,
+                    visited: false,
+                });
+            }
+        }
+    }
+
+    pub fn landing_pads(&self) -> &[LandingPad] {
+        &self.landing_pads
+    }
+
+    /// Middle of the pad on the surface and the surface normal there
+    pub fn pad_position(&self, pad: &LandingPad) -> (NVector2, NVector2) {
+        let planet = &self.planets[pad.planet];
+        let center = self.rigid_body_set[planet.body].translation();
+        let normal = vector![pad.angle.cos(), pad.angle.sin()];
+        (center + normal * planet.radius, normal)
+    }
+
+    /// Pad the ship is parked on
+    pub fn landed_on(&self) -> Option<usize> {
+        self.ship.as_ref().and_then(|ship| ship.landed)
+    }
+
+    /// Ship just touched `planet_body` coming in at `velocity`. Parks it if that was
+    /// a soft landing on a pad, returns false if it was a crash
+    pub(super) fn try_land(&mut self, planet_body: RigidBodyHandle, velocity: NVector2) -> bool {
+        let ship = match &self.ship {
+            Some(ship) if ship.landed.is_none() && !ship.failed => ship,
+            _ => return false,
+        };
+        let body = &self.rigid_body_set[ship.body];
+        let speed = velocity.norm();
+        let nose = body.rotation() * vector![0., -1.];
+        let position = *body.translation();
+
+        let pad = self.landing_pads.iter().position(|pad| {
+            let planet = &self.planets[pad.planet];
+            if planet.body != planet_body {
+                return false;
+            }
+            let center = self.rigid_body_set[planet.body].translation();
+            let normal = (position - center).normalize();
+            let pad_normal = vector![pad.angle.cos(), pad.angle.sin()];
+            // Distance along the surface from the middle of the pad
+            let off_center = normal.angle(&pad_normal) * planet.radius;
+            off_center < PAD_HALF_WIDTH && nose.dot(&normal) > LANDING_MIN_UPRIGHT
+        });
+        let pad = match pad {
+            Some(pad) if speed < LANDING_MAX_SPEED => pad,
+            _ => return false,
+        };
+
+        if !self.landing_pads[pad].visited && !self.completed {
+            self.landing_pads[pad].visited = true;
+            self.score += LANDING_POINTS;
+        }
+        if let Some(ship) = self.ship.as_mut() {
+            ship.landed = Some(pad);
+        }
+        self.park_ship();
+        self.events.push(SimEvent::Landed(pad));
+        true
+    }
+
+    /// Keeps a landed ship still on its pad, the pilot thrusting forward takes off.
+    /// Returns input the ship should get this step (before assists)
+    pub(super) fn update_landing(&mut self, input: &ShipInput) -> ShipInput {
+        let landed = self.landed_on().is_some();
+        if !landed {
+            return *input;
+        }
+        if input.forward > 0.0 {
+            if let Some(ship) = self.ship.as_mut() {
+                ship.landed = None;
+            }
+            return *input;
+        }
+        self.park_ship();
+        ShipInput::default()
+    }
+
+    /// Puts the ship upright just above its pad, not moving
+    fn park_ship(&mut self) {
+        let (ship_body, pad, half_length) = match &self.ship {
+            Some(ship) => match ship.landed {
+                Some(pad) => (
+                    ship.body,
+                    pad,
+                    ship.stats.collider.half_height + ship.stats.collider.radius,
+                ),
+                None => return,
+            },
+            None => return,
+        };
+        let (surface, normal) = self.pad_position(&self.landing_pads[pad]);
+        let position = surface + normal * (half_length + PARK_HEIGHT);
+        // Nose (-Y) points along the normal
+        let angle = normal.x.atan2(-normal.y);
+        let body = &mut self.rigid_body_set[ship_body];
+        body.set_position(Isometry::new(position, angle), true);
+        body.set_linvel(NVector2::zeros(), true);
+        body.set_angvel(0.0, true);
+    }
+}
@@ -0,0 +1,232 @@
+use super::{CollisionGroup, GateState, PlanetState, Ship, ShipStats, Simulation, WithGroup};
+
+use crate::math::NVector2;
+use crate::ruleset::Ruleset;
+
+use rapier2d::prelude::*;
+
+use rand::prelude::*;
+
+use std::f32::consts::PI;
+
+impl Simulation {
+    /// Seed of the non-random level of given length
+    pub fn fixed_seed(length: u16) -> u64 {
+        ((length << 6) + 321) as u64
+    }
+
+    /// Creates a simulation with generated level of given length and the ship at the start
+    pub fn new_level(seed: u64, ruleset: Ruleset, length: u16, ship: ShipStats) -> Self {
+        let mut sim = Simulation::new(seed, ruleset);
+        sim.spawn_many_planets_with_gates(length);
+        sim.spawn_landing_pads();
+        let fuel_budget = ship.fuel_capacity * length as f32;
+        sim.spawn_ship(vector![0., 0.], ship, fuel_budget);
+        if ruleset.fuel_mode {
+            sim.spawn_fuel_sources(fuel_budget);
+        }
+        sim
+    }
+
+    /// Spawns the ship
+    pub fn spawn_ship(&mut self, position: NVector2, stats: ShipStats, fuel: f32) {
+        assert!(self.ship.is_none(), "Can't spawn a second ship");
+
+        let flight = self
+            .ruleset
+            .flight_model
+            .unwrap_or(stats.flight_model)
+            .params();
+
+        // CCD so the ship can't fly through thin things at high speeds
+        let rigid_body = RigidBodyBuilder::new_dynamic()
+            .translation(position)
+            .can_sleep(false)
+            .ccd_enabled(true)
+            .linear_damping(flight.linear_damping)
+            .angular_damping(flight.angular_damping)
+            .build();
+        let shape = stats.collider;
+        let collider = ColliderBuilder::capsule_y(shape.half_height, shape.radius)
+            .position(Isometry::new(vector![0., shape.offset], 0.0))
+            .density(stats.mass / stats.collider_area())
+            .group(CollisionGroup::Player, &self.ruleset.collisions)
+            .active_events(ActiveEvents::INTERSECTION_EVENTS | ActiveEvents::CONTACT_EVENTS)
+            .build();
+
+        let ship_body_handle = self.rigid_body_set.insert(rigid_body);
+        let ship_col_handle = self.collider_set.insert_with_parent(
+            collider,
+            ship_body_handle,
+            &mut self.rigid_body_set,
+        );
+        self.physics_server.player_collider_handle = Some(ship_col_handle);
+
+        self.ship = Some(Ship::new(
+            ship_body_handle,
+            ship_col_handle,
+            stats,
+            flight,
+            fuel,
+        ));
+    }
+
+    /// Spawns a planet at given position with given radius
+    pub fn spawn_planet(
+        &mut self,
+        position: NVector2,
+        radius: f32,
+        color_a: (f32, f32, f32),
+        color_b: (f32, f32, f32),
+    ) {
+        let rigid_body = RigidBodyBuilder::new_static()
+            .translation(position)
+            .can_sleep(false)
+            .build();
+        let collider = ColliderBuilder::ball(radius)
+            .group(CollisionGroup::Planet, &self.ruleset.collisions)
+            .density(8.0)
+            .build();
+
+        let rigid_body_handle = self.rigid_body_set.insert(rigid_body);
+        self.collider_set
+            .insert_with_parent(collider, rigid_body_handle, &mut self.rigid_body_set);
+
+        self.planets.push(PlanetState {
+            body: rigid_body_handle,
+            radius,
+            color_a,
+            color_b,
+            refuel: false,
+        });
+    }
+
+    /// Spawns a gate at given position
+    pub fn spawn_gate(&mut self, position: NVector2, rotation: f32) {
+        let width = 15.0;
+        let height = 115.0;
+
+        let rigid_body = RigidBodyBuilder::new_static()
+            .translation(position)
+            .can_sleep(false)
+            .user_data(self.gate_count.into())
+            .rotation(rotation)
+            .build();
+
+        let area_collider = ColliderBuilder::cuboid(width * 0.3, height)
+            .sensor(true)
+            .group(CollisionGroup::GateSensor, &self.ruleset.collisions)
+            .build();
+        let gate_collider_1 = ColliderBuilder::ball(width)
+            .translation(vector![0., height])
+            .group(CollisionGroup::GateSolid, &self.ruleset.collisions)
+            .build();
+        let gate_collider_2 = ColliderBuilder::ball(width)
+            .translation(vector![0., -(height)])
+            .group(CollisionGroup::GateSolid, &self.ruleset.collisions)
+            .build();
+
+        let rigid_body_handle = self.rigid_body_set.insert(rigid_body);
+        self.collider_set.insert_with_parent(
+            area_collider,
+            rigid_body_handle,
+            &mut self.rigid_body_set,
+        );
+
+        self.collider_set.insert_with_parent(
+            gate_collider_1,
+            rigid_body_handle,
+            &mut self.rigid_body_set,
+        );
+        self.collider_set.insert_with_parent(
+            gate_collider_2,
+            rigid_body_handle,
+            &mut self.rigid_body_set,
+        );
+
+        self.gates.push(GateState {
+            body: rigid_body_handle,
+            gate_num: self.gate_count,
+            half_length: height,
+        });
+
+        self.gate_count += 1;
+    }
+
+    /// Spawns a planet with gates around it
+    pub fn spawn_planet_with_gates(&mut self, position: NVector2, radius: f32, gate_count: u16) {
+        assert!(gate_count < 6, "Gate count must be less than 6");
+
+        let hue = self.rng.gen::<f32>() * 250.;
+        let sat = self.rng.gen::<f32>() * 0.3 + 0.3;
+        let color_a = (hue, sat, 0.9);
+        let hue = hue + 135.;
+        let sat = self.rng.gen::<f32>() * 0.3 + 0.5;
+        let color_b = (hue, sat, 0.5);
+
+        self.spawn_planet(position, radius, color_a, color_b);
+
+        let direction = (self.rng.gen::<f32>() - 0.5).signum();
+
+        let start_angle = self.rng.gen::<f32>() * PI;
+
+        let angle_step = 2.0 * PI / (5.0 + self.rng.gen::<f32>() * 2.0);
+        for i in 0..gate_count {
+            let gate_offset: f32 = radius * (self.rng.gen::<f32>() + 1.2) + 100.;
+            let rot = Rotation::new(start_angle + angle_step * direction * i as f32);
+            let offset = rot.into_inner() * gate_offset;
+            let pos = vector![offset.re, offset.im] + position;
+            self.spawn_gate(pos, rot.angle() + PI / 2.0);
+        }
+    }
+
+    /// Spawns many planets at random positions with gates around them
+    pub fn spawn_many_planets_with_gates(&mut self, num_gates: u16) {
+        let mut planets: Vec<(NVector2, f32)> = Vec::new();
+
+        let radius_range = 300.0..700.0;
+
+        let mut gates_left = num_gates;
+
+        let mut last_position: NVector2 = vector![0., 0.];
+        let mut last_radius = 0.;
+        while gates_left > 0 {
+            let mut position_valid = false;
+            let radius = self.rng.gen_range(radius_range.clone());
+            let mut distance = (last_radius + radius) * (3.0 + self.rng.gen::<f32>());
+            let mut pos: NVector2 = vector![0., 0.];
+            while !position_valid {
+                let angle = self.rng.gen::<f32>() * PI * 2.0;
+                let rot = Rotation::new(angle);
+                let offset = rot.into_inner() * distance;
+                distance *= 1.05;
+                pos = vector![offset.re, offset.im] + last_position;
+
+                // Check if planet too close to other planets
+                position_valid = true;
+                for planet in planets.iter() {
+                    let dist = (pos - planet.0).norm();
+                    let min_dist = (radius + planet.1) * 2.8;
+                    if dist < min_dist {
+                        position_valid = false;
+                        break;
+                    }
+                }
+            }
+
+            let mut gate_count =
+                ((self.rng.gen_range(1..6) + self.rng.gen_range(0..6)) as f32 * 0.5).ceil() as u16;
+            if self.rng.gen_bool(0.3) {
+                gate_count = 0;
+            } else {
+                last_radius = radius;
+                last_position = pos;
+            }
+            gate_count = gate_count.min(gates_left);
+            gates_left -= gate_count;
+            self.spawn_planet_with_gates(pos, radius, gate_count);
+            self.spawn_asteroids_around_planet(pos, radius);
+            planets.push((pos, radius));
+        }
+    }
+}
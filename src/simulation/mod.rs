@@ -0,0 +1,580 @@
+//! Game simulation: physics, gravity, scoring, gates, asteroids and fuel.
+//! It doesn't know anything about graphics, audio or keyboard,
+//! it's advanced in fixed steps with a `ShipInput` for each one.
+
+use crate::math::{polygon, NPoint2, NVector2};
+use crate::ruleset::{Objective, Ruleset};
+
+use rapier2d::prelude::*;
+
+use rand::prelude::*;
+use rand_pcg::Pcg64;
+
+use serde::{Deserialize, Serialize};
+
+mod physics_server;
+use physics_server::*;
+
+mod input;
+pub use input::ShipInput;
+
+mod ship;
+pub use ship::{FuelUse, Ship, ShipStats};
+
+mod heat;
+
+mod landing;
+use landing::LandingPad;
+pub use landing::PAD_HALF_WIDTH;
+
+mod tether;
+use tether::Tether;
+
+mod shield;
+
+mod weapons;
+use weapons::Shot;
+
+mod fuel;
+use fuel::{FuelCanister, Station};
+pub use fuel::{Refuelling, ORBIT_MAX_ALTITUDE, ORBIT_MIN_ALTITUDE, STATION_DOCK_RADIUS};
+
+mod flight_model;
+pub use flight_model::{FlightModel, FlightParams};
+
+mod assists;
+pub use assists::{turn_toward, Assists, Orientation};
+
+mod stats;
+pub use stats::RunStats;
+
+mod groups;
+use groups::WithGroup;
+pub use groups::{CollisionGroup, CollisionMasks};
+
+mod asteroids;
+mod level;
+
+mod snapshot;
+pub use snapshot::Snapshot;
+
+mod rewind;
+pub use rewind::Rewind;
+
+mod replay;
+pub use replay::Replay;
+
+mod timeline;
+pub use timeline::{Marker, Timeline};
+
+mod ghost;
+pub use ghost::{Ghost, GhostFrame};
+
+/// Length of one simulation step (in seconds)
+pub const TIMESTEP: f32 = 1.0 / 60.0;
+
+const G: f32 = 10.0;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PlanetState {
+    pub body: RigidBodyHandle,
+    pub radius: f32,
+    /// Colors of the planet as (hue, saturation, value)
+    pub color_a: (f32, f32, f32),
+    pub color_b: (f32, f32, f32),
+    /// Ship can refuel by slowly orbiting it (in fuel mode)
+    pub refuel: bool,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct GateState {
+    pub body: RigidBodyHandle,
+    pub gate_num: u32,
+    /// Distance from the center to each post
+    pub half_length: f32,
+}
+
+/// Something that happened during a step, for frontends to react to
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SimEvent {
+    /// Ship hit something with given relative speed
+    ShipImpact(f32),
+    /// Ship went through the gate it should have
+    GatePassed(u32),
+    /// Ship landed softly on the pad with given index
+    Landed(usize),
+    /// Shield took a hit (and went down)
+    ShieldHit,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Simulation {
+    rng: Pcg64,
+    /// Level was generated from this
+    seed: u64,
+    ruleset: Ruleset,
+    physics_server: PhysicsServer,
+    rigid_body_set: RigidBodySet,
+    collider_set: ColliderSet,
+    planets: Vec<PlanetState>,
+    gates: Vec<GateState>,
+    /// Colliders (and bodies) of asteroids currently flying around
+    asteroids: Vec<(ColliderHandle, RigidBodyHandle)>,
+    /// Despawned asteroids (parked in the physics world) ready to be reused
+    asteroid_pool: Vec<(ColliderHandle, RigidBodyHandle)>,
+    /// Asteroids of the level's belts (they stay even when far from the ship)
+    belt_asteroids: Vec<ColliderHandle>,
+    /// How far the frontend can see from the ship (0 without a view)
+    view_radius: f32,
+    ship: Option<Ship>,
+    canisters: Vec<FuelCanister>,
+    stations: Vec<Station>,
+    landing_pads: Vec<LandingPad>,
+    tether: Option<Tether>,
+    /// Seconds until the tether can be fired again
+    tether_cooldown: f32,
+    /// Seconds the shield stays up
+    shield_time: f32,
+    /// Seconds until the shield can be raised again
+    shield_recharge: f32,
+    shots: Vec<Shot>,
+    /// Seconds until the next shot can be fired
+    fire_cooldown: f32,
+    score: i32,
+    steps: u32,
+    time_since_start: f32,
+    asteroid_spawn_timer: f32,
+    completed: bool,
+    gate_count: u32,
+    next_gate: u32,
+    stats: RunStats,
+    #[serde(skip)]
+    events: Vec<SimEvent>,
+}
+
+impl Simulation {
+    pub fn new(seed: u64, ruleset: Ruleset) -> Self {
+        Simulation {
+            rng: Pcg64::seed_from_u64(seed),
+            seed,
+            ruleset,
+            physics_server: PhysicsServer::new(),
+            rigid_body_set: RigidBodySet::new(),
+            collider_set: ColliderSet::new(),
+            planets: Vec::new(),
+            gates: Vec::new(),
+            asteroids: Vec::new(),
+            asteroid_pool: Vec::new(),
+            belt_asteroids: Vec::new(),
+            view_radius: 0.,
+            ship: None,
+            canisters: Vec::new(),
+            stations: Vec::new(),
+            landing_pads: Vec::new(),
+            tether: None,
+            tether_cooldown: 0.,
+            shield_time: 0.,
+            shield_recharge: 0.,
+            shots: Vec::new(),
+            fire_cooldown: 0.,
+            score: 30,
+            steps: 0,
+            time_since_start: 0.,
+            asteroid_spawn_timer: 0.,
+            completed: false,
+            gate_count: 0,
+            next_gate: 0,
+            stats: RunStats::default(),
+            events: Vec::new(),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn ruleset(&self) -> &Ruleset {
+        &self.ruleset
+    }
+
+    pub fn bodies(&self) -> &RigidBodySet {
+        &self.rigid_body_set
+    }
+
+    pub fn colliders(&self) -> &ColliderSet {
+        &self.collider_set
+    }
+
+    pub fn planets(&self) -> &[PlanetState] {
+        &self.planets
+    }
+
+    pub fn gates(&self) -> &[GateState] {
+        &self.gates
+    }
+
+    /// Asteroids currently flying around
+    pub fn asteroids(&self) -> &[(ColliderHandle, RigidBodyHandle)] {
+        &self.asteroids
+    }
+
+    pub fn ship(&self) -> Option<&Ship> {
+        self.ship.as_ref()
+    }
+
+    pub fn score(&self) -> i32 {
+        self.score
+    }
+
+    /// Steps simulated since the level was generated
+    pub fn steps(&self) -> u32 {
+        self.steps
+    }
+
+    pub fn time_since_start(&self) -> f32 {
+        self.time_since_start
+    }
+
+    /// How the level has been played so far
+    pub fn stats(&self) -> &RunStats {
+        &self.stats
+    }
+
+    /// Level is over (either completed or failed)
+    pub fn is_completed(&self) -> bool {
+        self.completed
+    }
+
+    pub fn is_failed(&self) -> bool {
+        self.ship.as_ref().map(|s| s.failed).unwrap_or(true)
+    }
+
+    pub fn gate_count(&self) -> u32 {
+        self.gate_count
+    }
+
+    pub fn next_gate(&self) -> u32 {
+        self.next_gate
+    }
+
+    /// Positions and masses of planets
+    fn gravity_sources(&self) -> Vec<(NVector2, f32)> {
+        self.planets
+            .iter()
+            .map(|planet| {
+                let body = &self.rigid_body_set[planet.body];
+                (*body.translation(), body.mass())
+            })
+            .collect()
+    }
+
+    /// Gravity acceleration at given position
+    pub fn gravity(&self, position: NVector2) -> NVector2 {
+        gravity_at(&self.gravity_sources(), position)
+    }
+
+    /// World space points (and normals) where colliders are touching right now
+    pub fn contact_points(&self) -> Vec<(NPoint2, NVector2)> {
+        self.physics_server.contact_points()
+    }
+
+    /// Lowers the score (the level fails on the next step if it's below 0)
+    pub fn take_points(&mut self, points: i32) {
+        self.score -= points;
+    }
+
+    /// Returns events that happened since last call
+    pub fn take_events(&mut self) -> Vec<SimEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Advances the simulation by `TIMESTEP`
+    pub fn step(&mut self, input: &ShipInput) {
+        let delta = TIMESTEP;
+        self.steps += 1;
+
+        // Tick timers
+        if !self.completed {
+            self.time_since_start += delta;
+        }
+        self.asteroid_spawn_timer += delta;
+
+        // Spawning and despawning asteroids around the ship
+        let ship_position = self
+            .ship
+            .as_ref()
+            .map(|ship| *self.rigid_body_set[ship.body].translation());
+        if let Some(center) = ship_position {
+            self.update_asteroids(center);
+        }
+
+        // Ship controls
+        // Taking off is up to the pilot (assists alone don't lift a landed ship)
+        let input = self.update_landing(input);
+        let input = self.assisted_input(&input);
+        self.update_tether(&input, delta);
+        self.update_weapons(&input, delta);
+        self.update_shield(&input, delta);
+        let cooling = ship_position
+            .map(|position| self.cooling_rate(position))
+            .unwrap_or(0.);
+        if !self.completed {
+            self.stats.record_assists(&input.assists, delta);
+        }
+        let burn_fuel = self.ruleset.fuel_mode && !self.completed;
+        let consumption = if burn_fuel {
+            self.ruleset.fuel_consumption
+        } else {
+            0.
+        };
+        let limited_rcs = burn_fuel && self.ruleset.limited_rcs;
+        if let Some(ship) = self.ship.as_mut() {
+            let used = ship.apply_input(&input, delta, consumption, limited_rcs);
+            ship.update_heat(&input, delta, cooling);
+            self.stats.fuel_used.add(&used);
+        }
+
+        // Calculating gravity forces
+        let planets_vector = self.gravity_sources();
+
+        // Pre physics
+        // (parked ship stays put)
+        let dynamic_bodies: Vec<RigidBodyHandle> = self
+            .ship
+            .iter()
+            .filter(|ship| ship.landed.is_none())
+            .map(|ship| ship.body)
+            .chain(self.asteroids.iter().map(|(_, body)| *body))
+            .collect();
+        for body_handle in dynamic_bodies {
+            let body = &mut self.rigid_body_set[body_handle];
+            let gravity_force = gravity_at(&planets_vector, *body.translation());
+            body.apply_force(gravity_force * body.mass(), true);
+        }
+        if let Some(ship) = &self.ship {
+            ship.physics_process(delta, &mut self.rigid_body_set[ship.body]);
+        }
+
+        // Physics
+        // (the solver takes the speed out of impacts, landings are judged by the speed before)
+        let ship_velocity = self
+            .ship
+            .as_ref()
+            .map(|ship| *self.rigid_body_set[ship.body].linvel());
+        self.physics_server
+            .step(&mut self.rigid_body_set, &mut self.collider_set);
+
+        self.handle_contacts(ship_velocity);
+
+        if self.ruleset.fuel_mode && !self.completed {
+            self.collect_fuel(delta);
+        }
+
+        // When player goes through a gate
+        let ship_moved = ship_position.zip(
+            self.ship
+                .as_ref()
+                .map(|ship| *self.rigid_body_set[ship.body].translation()),
+        );
+        let swept_through = ship_moved
+            .map(|(from, to)| self.crosses_next_gate(from, to))
+            .unwrap_or(false);
+        if !self.completed && (swept_through || self.sensor_hit_next_gate()) {
+            self.events.push(SimEvent::GatePassed(self.next_gate));
+            // "Select" next gate
+            self.next_gate += 1;
+            self.score += 30;
+        }
+
+        if self.score < 0 {
+            if let Some(ship) = self.ship.as_mut() {
+                ship.failed = true;
+                self.completed = true;
+            }
+        }
+
+        self.completed |= self.objective_done();
+    }
+
+    fn objective_done(&self) -> bool {
+        match self.ruleset.objective {
+            Objective::Gates => self.next_gate >= self.gate_count,
+            Objective::ClearField(count) => self.stats.asteroids_shot >= count,
+        }
+    }
+
+    /// Gate sensor reported the ship entering the gate it should go through
+    fn sensor_hit_next_gate(&self) -> bool {
+        if !self.physics_server.player_intersected {
+            return false;
+        }
+        // Get collider
+        let col_h = match self.physics_server.last_intersected {
+            Some(col_h) => col_h,
+            None => return false,
+        };
+        // Get body and check if gate number is the one that player should go through
+        match self.collider_set.get(col_h).and_then(|col| col.parent()) {
+            Some(body_h) => self.rigid_body_set[body_h].user_data == self.next_gate.into(),
+            None => false,
+        }
+    }
+
+    /// Ship moving from `from` to `to` in one step went between posts of the next gate.
+    /// Catches passes the thin sensor misses at high speeds
+    fn crosses_next_gate(&self, from: NVector2, to: NVector2) -> bool {
+        let gate = match self.gates.get(self.next_gate as usize) {
+            Some(gate) => gate,
+            None => return false,
+        };
+        let gate_position = self.rigid_body_set[gate.body].position();
+        let post_a = gate_position * point![0., gate.half_length];
+        let post_b = gate_position * point![0., -gate.half_length];
+        polygon::segments_intersect(from.into(), to.into(), post_a, post_b)
+    }
+
+    /// `ship_velocity` is the ship's velocity before the physics step
+    fn handle_contacts(&mut self, ship_velocity: Option<NVector2>) {
+        let mut contact_events_guard = self
+            .physics_server
+            .event_handler
+            .contact_events
+            .lock()
+            .unwrap();
+
+        let mut contact_events = contact_events_guard.clone();
+        contact_events_guard.clear();
+        drop(contact_events_guard);
+
+        for event in contact_events.drain(..) {
+            if let ContactEvent::Started(col1, col2) = event {
+                if let Some(pch) = self.physics_server.player_collider_handle {
+                    // One of them is the player
+                    if col1 == pch || col2 == pch {
+                        // Soft landings don't cost points
+                        let other = if col1 == pch { col2 } else { col1 };
+                        let other_body = self.collider_set.get(other).and_then(|c| c.parent());
+                        let planet = other_body.filter(|body| self.is_planet(*body));
+                        if let (Some(body), Some(velocity)) = (planet, ship_velocity) {
+                            if self.try_land(body, velocity) {
+                                continue;
+                            }
+                        }
+                        // Neither do hits the shield takes
+                        if self.shield_active() {
+                            self.shield_hit(other);
+                            continue;
+                        }
+                        let bh1 = self.collider_set.get(col1).and_then(|c| c.parent());
+                        let bh2 = self.collider_set.get(col2).and_then(|c| c.parent());
+                        if let (Some(bh1), Some(bh2)) = (bh1, bh2) {
+                            let b1 = &self.rigid_body_set[bh1];
+                            let b2 = &self.rigid_body_set[bh2];
+                            let vel_dif_mag = (b1.linvel() - b2.linvel()).norm();
+                            self.events.push(SimEvent::ShipImpact(vel_dif_mag));
+                        }
+                        if !self.completed {
+                            self.score -= 10;
+                        }
+                    }
+                    // None of them is the player
+                    // (asteroids never touch each other, see `CollisionGroup`)
+                    else if self.is_shot(col1) {
+                        self.shot_hit(col1, col2);
+                    } else if self.is_shot(col2) {
+                        self.shot_hit(col2, col1);
+                    } else {
+                        // Destroy asteroids
+                        if self.is_asteroid(col1) {
+                            self.remove_asteroid(col1);
+                        }
+                        if self.is_asteroid(col2) {
+                            self.remove_asteroid(col2);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn is_planet(&self, body: RigidBodyHandle) -> bool {
+        self.planets.iter().any(|planet| planet.body == body)
+    }
+
+    pub fn remove_rigidbody(&mut self, rigid_body: RigidBodyHandle) {
+        self.rigid_body_set.remove(
+            rigid_body,
+            &mut self.physics_server.island_manager,
+            &mut self.collider_set,
+            &mut self.physics_server.joint_set,
+        );
+    }
+}
+
+/// Gravity acceleration at given position
+pub fn gravity_at(planets: &[(NVector2, f32)], position: NVector2) -> NVector2 {
+    let mut gravity = vector![0., 0.];
+    for planet_v in planets.iter() {
+        let dir = planet_v.0 - position;
+        let dist = dir.norm();
+        if dist > 7777.7 {
+            continue;
+        }
+        gravity += dir.normalize() * G * planet_v.1 / dir.norm_squared().max(0.01);
+    }
+    gravity
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Level with the ship `offset` away from gate 0 (in the gate's space,
+    /// +X goes through it) flying through the gate's plane at `speed`
+    fn ship_at_gate(offset: NVector2, speed: f32) -> Simulation {
+        let mut sim = Simulation::new_level(1, Ruleset::classic(), 3, ShipStats::default());
+        // Nothing should get in the way
+        let asteroids: Vec<ColliderHandle> = sim.asteroids.iter().map(|(col, _)| *col).collect();
+        for col in asteroids {
+            sim.despawn_asteroid(col);
+        }
+        let gate_position = *sim.rigid_body_set[sim.gates[0].body].position();
+        let ship_body = sim.ship.as_ref().unwrap().body;
+        let body = &mut sim.rigid_body_set[ship_body];
+        body.set_translation((gate_position * NPoint2::from(offset)).coords, true);
+        body.set_rotation(gate_position.rotation.angle(), true);
+        body.set_linvel(gate_position.rotation * vector![speed, 0.], true);
+        sim
+    }
+
+    fn gate_passed(sim: &mut Simulation, steps: u32) -> bool {
+        let mut passed = false;
+        for _ in 0..steps {
+            sim.step(&ShipInput::default());
+            passed |= sim.take_events().contains(&SimEvent::GatePassed(0));
+        }
+        passed
+    }
+
+    #[test]
+    fn fast_ship_passes_gate() {
+        for speed in [2000.0, 20000.0, 60000.0] {
+            let mut sim = ship_at_gate(vector![-300., 0.], speed);
+            assert!(gate_passed(&mut sim, 20), "gate not passed at {}", speed);
+            assert!(sim.next_gate() > 0);
+        }
+    }
+
+    #[test]
+    fn fast_ship_beside_gate_misses_it() {
+        let half_length = Simulation::new_level(1, Ruleset::classic(), 3, ShipStats::default())
+            .gates[0]
+            .half_length;
+        for side in [-1.0, 1.0] {
+            let offset = vector![-300., side * (half_length + 200.)];
+            let mut sim = ship_at_gate(offset, 20000.0);
+            assert!(!gate_passed(&mut sim, 20));
+            assert_eq!(sim.next_gate(), 0);
+        }
+    }
+}
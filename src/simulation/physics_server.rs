@@ -3,18 +3,28 @@ use rapier2d::prelude::*;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Mutex;
 
-use crate::math::NVector2;
+use serde::{Deserialize, Serialize};
 
+use crate::math::{NPoint2, NVector2};
+
+use super::TIMESTEP;
+
+/// Pipeline, CCD solver and event handler only hold data used during a step,
+/// so they are recreated instead of being cloned or serialized
+#[derive(Serialize, Deserialize)]
 pub struct PhysicsServer {
     gravity: NVector2,
     integration_parameters: IntegrationParameters,
+    #[serde(skip, default = "PhysicsPipeline::new")]
     physics_pipeline: PhysicsPipeline,
     pub island_manager: IslandManager,
     broad_phase: BroadPhase,
     narrow_phase: NarrowPhase,
     pub joint_set: JointSet,
+    #[serde(skip, default = "CCDSolver::new")]
     ccd_solver: CCDSolver,
     physics_hooks: (),
+    #[serde(skip, default = "MyEventHandler::new")]
     pub event_handler: MyEventHandler,
     pub player_collider_handle: Option<ColliderHandle>,
     pub player_intersected: bool,
@@ -26,7 +36,10 @@ impl PhysicsServer {
         /* Create other structures necessary for the simulation. */
         Self {
             gravity: vector![0.0, 0.0],
-            integration_parameters: IntegrationParameters::default(),
+            integration_parameters: IntegrationParameters {
+                dt: TIMESTEP,
+                ..IntegrationParameters::default()
+            },
             physics_pipeline: PhysicsPipeline::new(),
             island_manager: IslandManager::new(),
             broad_phase: BroadPhase::new(),
@@ -41,6 +54,27 @@ impl PhysicsServer {
         }
     }
 
+    /// World space points (and normals) where colliders are touching right now
+    pub fn contact_points(&self) -> Vec<(NPoint2, NVector2)> {
+        let mut points = Vec::new();
+        for pair in self.narrow_phase.contact_pairs() {
+            if !pair.has_any_active_contact {
+                continue;
+            }
+            for manifold in pair.manifolds.iter() {
+                for contact in manifold.data.solver_contacts.iter() {
+                    points.push((contact.point, manifold.data.normal));
+                }
+            }
+        }
+        points
+    }
+
+    /// Colliders `a` and `b` (one of them a sensor) overlapped after the last step
+    pub fn intersecting(&self, a: ColliderHandle, b: ColliderHandle) -> bool {
+        self.narrow_phase.intersection_pair(a, b) == Some(true)
+    }
+
     pub fn step(&mut self, rigid_body_set: &mut RigidBodySet, collider_set: &mut ColliderSet) {
         self.physics_pipeline.step(
             &self.gravity,
@@ -80,6 +114,26 @@ impl PhysicsServer {
     }
 }
 
+impl Clone for PhysicsServer {
+    fn clone(&self) -> Self {
+        Self {
+            gravity: self.gravity,
+            integration_parameters: self.integration_parameters,
+            physics_pipeline: PhysicsPipeline::new(),
+            island_manager: self.island_manager.clone(),
+            broad_phase: self.broad_phase.clone(),
+            narrow_phase: self.narrow_phase.clone(),
+            joint_set: self.joint_set.clone(),
+            ccd_solver: CCDSolver::new(),
+            physics_hooks: (),
+            event_handler: MyEventHandler::new(),
+            player_collider_handle: self.player_collider_handle,
+            player_intersected: self.player_intersected,
+            last_intersected: self.last_intersected,
+        }
+    }
+}
+
 pub struct MyEventHandler {
     pub player_intersected: AtomicBool,
     pub collider1: Mutex<Option<ColliderHandle>>,
@@ -0,0 +1,410 @@
+use super::{ShipInput, ShipStats, Simulation, TIMESTEP};
+
+use crate::ruleset::Ruleset;
+
+use serde::{Deserialize, Serialize};
+
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+/// How a run ended up
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct RunResult {
+    pub completed: bool,
+    pub failed: bool,
+    pub time: f32,
+    pub score: i32,
+}
+
+/// Something that happened to a run besides flying it
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+enum RunEvent {
+    /// Started rewinding, which costs the ruleset's `rewind_cost`.
+    /// Going back past it moves it along, the cost isn't undone
+    Rewind,
+    /// Loaded a quicksave that isn't part of the recorded run
+    /// (from an earlier session, or rewound past), so it can't be simulated again
+    ForeignLoad,
+}
+
+/// Settings of a run and the input of every step, enough to simulate it again.
+/// Recording follows the simulation's step count, so rewinding or loading
+/// overwrites the part of the run that got undone
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Replay {
+    pub seed: u64,
+    pub length: u16,
+    pub ruleset: Ruleset,
+    /// Name of the ship's file (for drawing it)
+    pub ship_id: String,
+    pub ship: ShipStats,
+    inputs: Vec<ShipInput>,
+    /// Every change of the view radius with the step it was made at
+    views: Vec<(u32, f32)>,
+    /// Events with the step they take effect at (when the simulation gets there), in order
+    events: Vec<(u32, RunEvent)>,
+    /// Step of the quicksave made during the recorded run (`None` if there's none,
+    /// or the run went back before it)
+    #[serde(skip)]
+    quicksave: Option<u32>,
+    /// What the run ended with, `verify` checks it
+    claimed: Option<RunResult>,
+}
+
+impl Replay {
+    pub fn new(seed: u64, length: u16, ruleset: Ruleset, ship_id: &str, ship: ShipStats) -> Self {
+        Replay {
+            seed,
+            length,
+            ruleset,
+            ship_id: ship_id.to_string(),
+            ship,
+            inputs: Vec::new(),
+            views: Vec::new(),
+            events: Vec::new(),
+            quicksave: None,
+            claimed: None,
+        }
+    }
+
+    /// Generates the level the run starts on
+    pub fn start(&self) -> Simulation {
+        let mut sim = Simulation::new_level(self.seed, self.ruleset, self.length, self.ship);
+        self.apply_events(&mut sim);
+        sim
+    }
+
+    /// Applies the events of the step `sim` is at
+    fn apply_events(&self, sim: &mut Simulation) {
+        let step = sim.steps();
+        let from = self.events.partition_point(|(at, _)| *at < step);
+        for (_, event) in self.events[from..].iter().take_while(|(at, _)| *at == step) {
+            match event {
+                RunEvent::Rewind => sim.take_points(self.ruleset.rewind_cost),
+                RunEvent::ForeignLoad => {}
+            }
+        }
+    }
+
+    /// Has to be called before every simulation step with its input
+    /// (steps after finishing the level aren't recorded)
+    pub fn record(&mut self, sim: &Simulation, input: &ShipInput) {
+        if sim.is_completed() {
+            return;
+        }
+        let step = sim.steps() as usize;
+        self.inputs.truncate(step);
+        // Gaps (after loading a quicksave from elsewhere) can't be filled in,
+        // the load is recorded so `verify` rejects the run
+        while self.inputs.len() < step {
+            self.inputs.push(ShipInput::default());
+        }
+        self.inputs.push(*input);
+
+        self.views.retain(|(at, _)| *at < step as u32);
+        let view_radius = self.views.last().map(|(_, radius)| *radius).unwrap_or(0.0);
+        if sim.view_radius() != view_radius {
+            self.views.push((step as u32, sim.view_radius()));
+        }
+    }
+
+    /// Has to be called when rewinding starts (after it took its cost)
+    pub fn rewind_started(&mut self, sim: &Simulation) {
+        self.events.push((sim.steps(), RunEvent::Rewind));
+    }
+
+    /// Has to be called after the simulation went back to an earlier state of the run
+    /// (a rewind restored a snapshot)
+    pub fn went_back(&mut self, sim: &Simulation) {
+        let step = sim.steps();
+        // Rewinds stay paid for, the rest happened in the undone part
+        self.events
+            .retain(|(at, event)| *at <= step || *event == RunEvent::Rewind);
+        for (at, _) in &mut self.events {
+            *at = (*at).min(step);
+        }
+        if self.quicksave > Some(step) {
+            self.quicksave = None;
+        }
+    }
+
+    /// Has to be called after a quick save
+    pub fn quicksaved(&mut self, sim: &Simulation) {
+        self.quicksave = Some(sim.steps());
+    }
+
+    /// Has to be called after a quick load
+    pub fn quickloaded(&mut self, sim: &Simulation) {
+        let recorded = self.quicksave == Some(sim.steps());
+        self.went_back(sim);
+        if !recorded {
+            self.events.push((sim.steps(), RunEvent::ForeignLoad));
+        }
+    }
+
+    /// Remembers what the run ended with
+    pub fn finish(&mut self, sim: &Simulation) {
+        // Steps that were rewound right before the end
+        self.inputs.truncate(sim.steps() as usize);
+        self.claimed = Some(RunResult {
+            completed: sim.is_completed(),
+            failed: sim.is_failed(),
+            time: sim.time_since_start(),
+            score: sim.score(),
+        });
+    }
+
+    /// Input for the step after `step` steps (`None` after the end)
+    pub fn input(&self, step: u32) -> Option<ShipInput> {
+        self.inputs.get(step as usize).copied()
+    }
+
+    /// Simulates the next step of the run (returns `false` after the end)
+    pub fn step(&self, sim: &mut Simulation) -> bool {
+        let step = sim.steps();
+        let input = match self.input(step) {
+            Some(input) => input,
+            None => return false,
+        };
+        if let Ok(index) = self.views.binary_search_by_key(&step, |(at, _)| *at) {
+            sim.set_view_radius(self.views[index].1);
+        }
+        sim.step(&input);
+        self.apply_events(sim);
+        true
+    }
+
+    /// Number of recorded steps
+    pub fn len(&self) -> u32 {
+        self.inputs.len() as u32
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inputs.is_empty()
+    }
+
+    /// Simulates the whole run again
+    pub fn resimulate(&self) -> Simulation {
+        let mut sim = self.start();
+        while self.step(&mut sim) {}
+        sim
+    }
+
+    /// Simulates the run again (with the cost of its rewinds) and compares how it ended
+    /// with what was claimed. Returns the result if they match
+    pub fn verify(&self) -> Result<RunResult, String> {
+        let claimed = self.claimed.ok_or("Replay has no result")?;
+        if self
+            .events
+            .iter()
+            .any(|(_, event)| *event == RunEvent::ForeignLoad)
+        {
+            return Err("Replay loads a quicksave that isn't part of it".to_string());
+        }
+        // Only rewinds are left
+        let rewinds = self.events.len() as u32;
+        if self
+            .ruleset
+            .rewind_limit
+            .map_or(false, |limit| rewinds > limit)
+        {
+            return Err(format!(
+                "Replay rewinds {} times, more than allowed",
+                rewinds
+            ));
+        }
+        let sim = self.resimulate();
+        let result = RunResult {
+            completed: sim.is_completed(),
+            failed: sim.is_failed(),
+            time: sim.time_since_start(),
+            score: sim.score(),
+        };
+        let same = result.completed == claimed.completed
+            && result.failed == claimed.failed
+            && result.score == claimed.score
+            && (result.time - claimed.time).abs() < TIMESTEP / 2.0;
+        if same {
+            Ok(result)
+        } else {
+            Err(format!(
+                "Replay doesn't match its result: claimed {:?}, simulated {:?}",
+                claimed, result
+            ))
+        }
+    }
+
+    pub fn save(&self, path: &str) -> bincode::Result<()> {
+        if let Some(dir) = Path::new(path).parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let writer = BufWriter::new(File::create(path)?);
+        bincode::serialize_into(writer, self)
+    }
+
+    pub fn load(path: &str) -> bincode::Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        bincode::deserialize_from(reader)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::simulation::{Rewind, Snapshot};
+
+    /// A run recorded the way the game does it
+    struct Run {
+        sim: Simulation,
+        replay: Replay,
+        rewind: Rewind,
+        /// Last quicksave and what rewinding had cost by then
+        quicksave: Option<(Snapshot, i32)>,
+    }
+
+    impl Run {
+        fn new() -> Self {
+            let ruleset = Ruleset::classic();
+            let ship = ShipStats::default();
+            let mut sim = Simulation::new_level(3, ruleset, 3, ship);
+            sim.set_view_radius(1500.0);
+            Run {
+                sim,
+                replay: Replay::new(3, 3, ruleset, "test", ship),
+                rewind: Rewind::default(),
+                quicksave: None,
+            }
+        }
+
+        fn fly(&mut self, steps: u32, forward: f32, rotate_left: f32) {
+            let input = ShipInput {
+                forward,
+                rotate_left,
+                ..Default::default()
+            };
+            for _ in 0..steps {
+                self.replay.record(&self.sim, &input);
+                self.sim.step(&input);
+                self.rewind.record(&self.sim);
+            }
+        }
+
+        fn rewind(&mut self, steps: u32) {
+            assert!(self.rewind.start(&mut self.sim));
+            self.replay.rewind_started(&self.sim);
+            for _ in 0..steps {
+                if self.rewind.step_back(&mut self.sim) {
+                    self.replay.went_back(&self.sim);
+                }
+            }
+            self.rewind.stop();
+        }
+
+        fn quicksave(&mut self) {
+            self.quicksave = Some((self.sim.snapshot(), self.rewind.cost()));
+            self.replay.quicksaved(&self.sim);
+        }
+
+        /// Loads the last quicksave, or `foreign` (as if read from a file) if there's none
+        fn quickload(&mut self, foreign: Option<Snapshot>) {
+            let (snapshot, cost) = match self.quicksave.clone() {
+                Some(quicksave) => quicksave,
+                None => (foreign.unwrap(), 0),
+            };
+            self.sim.restore(&snapshot);
+            self.sim.take_points(self.rewind.cost() - cost);
+            self.rewind.clear();
+            self.replay.quickloaded(&self.sim);
+        }
+
+        fn finish(mut self) -> (Simulation, Replay) {
+            self.replay.finish(&self.sim);
+            (self.sim, self.replay)
+        }
+    }
+
+    /// Steps, score, time and where every body is
+    fn state(sim: &Simulation) -> (u32, i32, f32, Vec<[f32; 3]>) {
+        let bodies = sim
+            .rigid_body_set
+            .iter()
+            .map(|(_, body)| {
+                let position = body.position();
+                let translation = position.translation.vector;
+                [translation.x, translation.y, position.rotation.angle()]
+            })
+            .collect();
+        (sim.steps(), sim.score(), sim.time_since_start(), bodies)
+    }
+
+    /// Flies, rewinds twice and goes back to a quicksave made during the run
+    fn rewound_run() -> (Simulation, Replay) {
+        let mut run = Run::new();
+        run.fly(120, 0.4, 0.2);
+        run.quicksave();
+        run.fly(60, 0.6, 0.0);
+        run.rewind(30);
+        run.fly(40, 0.2, 0.5);
+        run.rewind(12);
+        run.quickload(None);
+        run.fly(90, 0.5, 0.1);
+        run.finish()
+    }
+
+    #[test]
+    fn replay_with_rewinds_and_quickloads_resimulates_the_run() {
+        let (sim, replay) = rewound_run();
+        let classic = Ruleset::classic();
+        assert_eq!(replay.len(), sim.steps());
+        // Both rewinds stay paid for after loading
+        let rewinds = replay.events.iter().filter(|(_, e)| *e == RunEvent::Rewind);
+        assert_eq!(rewinds.count(), 2);
+
+        assert_eq!(state(&replay.resimulate()), state(&sim));
+        let result = replay.verify().unwrap();
+        assert_eq!(result.score, sim.score());
+        let mut no_rewinds = Run::new();
+        no_rewinds.fly(120, 0.4, 0.2);
+        no_rewinds.fly(90, 0.5, 0.1);
+        let (no_rewinds, _) = no_rewinds.finish();
+        assert_eq!(result.score, no_rewinds.score() - 2 * classic.rewind_cost);
+    }
+
+    #[test]
+    fn replay_loading_a_foreign_quicksave_fails_to_verify() {
+        let mut other = Run::new();
+        other.fly(60, 1.0, 0.0);
+        let foreign = other.sim.snapshot();
+
+        let mut run = Run::new();
+        run.fly(30, 0.3, 0.3);
+        run.quickload(Some(foreign));
+        run.fly(30, 0.3, 0.3);
+        let (_, replay) = run.finish();
+        assert!(replay.verify().is_err());
+    }
+
+    #[test]
+    fn tampered_replay_fails_to_verify() {
+        // Claimed score doesn't match
+        let (_, mut replay) = rewound_run();
+        replay.claimed.as_mut().unwrap().score += 1;
+        assert!(replay.verify().is_err());
+
+        // Rewind cost left out
+        let (_, mut replay) = rewound_run();
+        replay.events.pop();
+        assert!(replay.verify().is_err());
+
+        // More rewinds than the ruleset allows
+        let (_, mut replay) = rewound_run();
+        let limit = replay.ruleset.rewind_limit.unwrap();
+        let (at, _) = replay.events[0];
+        while replay.events.len() as u32 <= limit {
+            replay.events.push((at, RunEvent::Rewind));
+        }
+        assert!(replay.verify().is_err());
+    }
+}
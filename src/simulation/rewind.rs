@@ -0,0 +1,108 @@
+use super::{Simulation, Snapshot, TIMESTEP};
+
+use std::collections::VecDeque;
+
+/// Steps between two recorded snapshots
+const SNAPSHOT_INTERVAL: u32 = 6;
+
+/// Keeps snapshots of the last few seconds so the simulation can be played backward.
+/// Score and gate progress are part of the snapshots, so they roll back with the world,
+/// only the cost of rewinding is taken again after every restore
+#[derive(Default)]
+pub struct Rewind {
+    /// Snapshots (oldest first) with the total cost that was already taken when each was made
+    snapshots: VecDeque<(Snapshot, i32)>,
+    steps: u32,
+    rewinding: bool,
+    used: u32,
+    cost: i32,
+}
+
+impl Rewind {
+    pub fn is_rewinding(&self) -> bool {
+        self.rewinding
+    }
+
+    /// How many more rewinds the ruleset allows (`None` means unlimited)
+    pub fn rewinds_left(&self, sim: &Simulation) -> Option<u32> {
+        sim.ruleset()
+            .rewind_limit
+            .map(|limit| limit.saturating_sub(self.used))
+    }
+
+    /// Points all rewinds so far cost
+    pub fn cost(&self) -> i32 {
+        self.cost
+    }
+
+    /// Forgets recorded snapshots (e.g. after loading a different state)
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+        self.steps = 0;
+        self.rewinding = false;
+    }
+
+    /// Has to be called after every simulation step
+    pub fn record(&mut self, sim: &Simulation) {
+        self.steps += 1;
+        if self.steps < SNAPSHOT_INTERVAL {
+            return;
+        }
+        self.steps = 0;
+
+        let max_snapshots =
+            (sim.ruleset().rewind_seconds / (TIMESTEP * SNAPSHOT_INTERVAL as f32)) as usize;
+        while self.snapshots.len() >= max_snapshots.max(1) {
+            self.snapshots.pop_front();
+        }
+        self.snapshots.push_back((sim.snapshot(), self.cost));
+    }
+
+    /// Starts rewinding and takes its cost from the score. Returns false if it was
+    /// already rewinding or the ruleset doesn't allow it
+    pub fn start(&mut self, sim: &mut Simulation) -> bool {
+        if self.rewinding {
+            return false;
+        }
+        // Nothing to undo after finishing the level
+        let won = sim.is_completed() && !sim.is_failed();
+        if won || self.snapshots.is_empty() || self.rewinds_left(sim) == Some(0) {
+            return false;
+        }
+        let cost = sim.ruleset().rewind_cost;
+        self.used += 1;
+        self.cost += cost;
+        sim.take_points(cost);
+        self.rewinding = true;
+        self.steps = 0;
+        true
+    }
+
+    pub fn stop(&mut self) {
+        self.rewinding = false;
+        self.steps = 0;
+    }
+
+    /// Goes back in time by one simulation step (instead of calling `Simulation::step`).
+    /// Returns true if the simulation was changed
+    pub fn step_back(&mut self, sim: &mut Simulation) -> bool {
+        if !self.rewinding {
+            return false;
+        }
+        self.steps += 1;
+        if self.steps < SNAPSHOT_INTERVAL {
+            return false;
+        }
+        self.steps = 0;
+
+        // Oldest snapshot is kept so there's always something to resume from
+        let (snapshot, cost) = match self.snapshots.len() {
+            0 => return false,
+            1 => self.snapshots.front().unwrap().clone(),
+            _ => self.snapshots.pop_back().unwrap(),
+        };
+        sim.restore(&snapshot);
+        sim.take_points(self.cost - cost);
+        true
+    }
+}
@@ -0,0 +1,90 @@
+//! Deflector shield: for a few seconds the next collision costs no points
+//! and knocks small asteroids away. Recharges afterwards, in fuel mode it also costs fuel
+
+use super::{ShipInput, SimEvent, Simulation};
+
+use crate::math::polygon;
+
+use rapier2d::prelude::*;
+
+/// Seconds the shield stays up (unless something hits it sooner)
+const SHIELD_DURATION: f32 = 3.0;
+/// Seconds after the shield goes down before it can be raised again
+const SHIELD_RECHARGE: f32 = 8.0;
+/// Fuel raising the shield takes (in fuel mode)
+const SHIELD_FUEL: f32 = 5.0;
+/// Asteroids smaller than this get knocked away
+const MAX_BOUNCE_AREA: f32 = 6000.0;
+/// Speed small asteroids fly away at (relative to the ship)
+const BOUNCE_SPEED: f32 = 400.0;
+
+impl Simulation {
+    pub fn shield_active(&self) -> bool {
+        self.shield_time > 0.
+    }
+
+    /// Seconds until the shield can be raised again
+    pub fn shield_recharge(&self) -> f32 {
+        self.shield_recharge
+    }
+
+    /// Raises the shield when `input.shield` is held and it's charged,
+    /// lowers it once its time is up
+    pub(super) fn update_shield(&mut self, input: &ShipInput, delta: f32) {
+        if self.shield_active() {
+            self.shield_time = (self.shield_time - delta).max(0.);
+            if !self.shield_active() {
+                self.shield_recharge = SHIELD_RECHARGE;
+            }
+            return;
+        }
+        self.shield_recharge = (self.shield_recharge - delta).max(0.);
+
+        if !input.shield || self.shield_recharge > 0. {
+            return;
+        }
+        let fuel_mode = self.ruleset.fuel_mode;
+        let ship = match self.ship.as_mut() {
+            Some(ship) if !ship.failed => ship,
+            _ => return,
+        };
+        if fuel_mode {
+            if ship.fuel < SHIELD_FUEL {
+                return;
+            }
+            ship.fuel -= SHIELD_FUEL;
+        }
+        self.shield_time = SHIELD_DURATION;
+    }
+
+    /// Ship hit `other` while the shield was up. Takes the hit, knocks `other` away
+    /// if it's a small asteroid
+    pub(super) fn shield_hit(&mut self, other: ColliderHandle) {
+        self.shield_time = 0.;
+        self.shield_recharge = SHIELD_RECHARGE;
+        self.events.push(SimEvent::ShieldHit);
+
+        let ship_body = match &self.ship {
+            Some(ship) => ship.body,
+            None => return,
+        };
+        if !self.is_asteroid(other) {
+            return;
+        }
+        let small = self.collider_set[other]
+            .shape()
+            .as_convex_polygon()
+            .map(|shape| polygon::area(shape.points()) < MAX_BOUNCE_AREA)
+            .unwrap_or(false);
+        let asteroid_body = match self.collider_set[other].parent() {
+            Some(body) if small => body,
+            _ => return,
+        };
+        let ship = &self.rigid_body_set[ship_body];
+        let (ship_position, ship_linvel) = (*ship.translation(), *ship.linvel());
+        let asteroid = &mut self.rigid_body_set[asteroid_body];
+        if let Some(away) = (asteroid.translation() - ship_position).try_normalize(0.01) {
+            asteroid.set_linvel(ship_linvel + away * BOUNCE_SPEED, true);
+        }
+    }
+}
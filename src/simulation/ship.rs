@@ -0,0 +1,328 @@
+use super::heat::{BOOST_HEAT, BOOST_MULTIPLIER, OVERHEAT_COOLDOWN};
+use super::{FlightModel, FlightParams, ShipInput};
+
+use crate::math::NVector2;
+
+use rapier2d::prelude::*;
+
+use serde::{Deserialize, Serialize};
+
+/// Capsule collider of a ship (along its Y axis)
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ShipCollider {
+    /// Half of the distance between centers of the round ends
+    pub half_height: f32,
+    pub radius: f32,
+    /// Offset along Y from the center of the sprite
+    pub offset: f32,
+}
+
+/// How a ship handles
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ShipStats {
+    pub collider: ShipCollider,
+    pub mass: f32,
+    /// Force of the main engine
+    pub main_thrust: f32,
+    /// Force of the side and reverse thrusters
+    pub side_thrust: f32,
+    pub rotation_torque: f32,
+    /// Fuel the ship gets for each gate of the level in fuel mode
+    pub fuel_capacity: f32,
+    /// Fuel used per second by the main engine at full power,
+    /// other thrusters use fuel in proportion to their force
+    pub burn_rate: f32,
+    /// Rotation thrusters have their own tank, this big
+    #[serde(default = "default_rcs_capacity")]
+    pub rcs_capacity: f32,
+    /// RCS fuel used per second of rotating at full torque
+    #[serde(default = "default_rcs_burn_rate")]
+    pub rcs_burn_rate: f32,
+    /// Used unless the ruleset has its own
+    #[serde(default)]
+    pub flight_model: FlightModel,
+}
+
+impl Default for ShipStats {
+    /// All-round ship
+    fn default() -> Self {
+        ShipStats {
+            collider: ShipCollider {
+                half_height: 25.0,
+                radius: 14.0,
+                offset: -3.0,
+            },
+            mass: 2000.0,
+            main_thrust: 420000.0,
+            side_thrust: 140000.0,
+            rotation_torque: 1500000.0,
+            fuel_capacity: 42.0,
+            burn_rate: 10.0,
+            rcs_capacity: default_rcs_capacity(),
+            rcs_burn_rate: default_rcs_burn_rate(),
+            flight_model: FlightModel::Newtonian,
+        }
+    }
+}
+
+fn default_rcs_capacity() -> f32 {
+    100.0
+}
+
+fn default_rcs_burn_rate() -> f32 {
+    4.0
+}
+
+impl ShipStats {
+    /// Area of the collider (for getting density from mass)
+    pub fn collider_area(&self) -> f32 {
+        let ShipCollider {
+            half_height,
+            radius,
+            ..
+        } = self.collider;
+        4.0 * half_height * radius + std::f32::consts::PI * radius * radius
+    }
+
+    /// Moment of inertia around the center of the collider (with uniform density,
+    /// like the physics engine computes it)
+    pub fn angular_inertia(&self) -> f32 {
+        let ShipCollider {
+            half_height,
+            radius,
+            ..
+        } = self.collider;
+        let density = self.mass / self.collider_area();
+        let rectangle_mass = density * 4.0 * half_height * radius;
+        let ends_mass = density * std::f32::consts::PI * radius * radius;
+        // Distance of a half disc's centroid from its flat side
+        let ends_centroid = 4.0 * radius / (3.0 * std::f32::consts::PI);
+        rectangle_mass * (radius * radius + half_height * half_height) / 3.0
+            + ends_mass
+                * (radius * radius / 2.0
+                    + half_height * half_height
+                    + 2.0 * half_height * ends_centroid)
+    }
+}
+
+/// Fuel each thruster burned (in one step or over a whole run)
+#[derive(Clone, Copy, Default, PartialEq, Debug, Serialize, Deserialize)]
+pub struct FuelUse {
+    pub main: f32,
+    pub reverse: f32,
+    pub left: f32,
+    pub right: f32,
+    /// Rotation thrusters (from their own tank)
+    pub rcs: f32,
+}
+
+impl FuelUse {
+    /// Fuel from the main tank
+    pub fn engines(&self) -> f32 {
+        self.main + self.reverse + self.left + self.right
+    }
+
+    pub fn add(&mut self, other: &FuelUse) {
+        self.main += other.main;
+        self.reverse += other.reverse;
+        self.left += other.left;
+        self.right += other.right;
+        self.rcs += other.rcs;
+    }
+}
+
+/// Player's ship: turns inputs into movement and keeps track of fuel
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Ship {
+    pub body: RigidBodyHandle,
+    pub collider: ColliderHandle,
+    pub stats: ShipStats,
+    /// Flight model the ship ended up with (its own or the ruleset's)
+    pub flight: FlightParams,
+    move_vec: NVector2, // force applied on next phys process (in local space)
+    rot: f32,           // torque applied on next phys process
+    pub fuel: f32,
+    /// Most fuel the ship can hold
+    pub tank: f32,
+    /// Fuel of rotation thrusters
+    pub rcs: f32,
+    /// 0.0 is cold, at 1.0 the ship overheats
+    pub heat: f32,
+    /// Seconds until thrusters work again after overheating
+    pub overheat_timer: f32,
+    boosting: bool,
+    /// Landing pad the ship is parked on
+    pub landed: Option<usize>,
+    pub failed: bool,
+}
+
+impl Ship {
+    pub fn new(
+        body: RigidBodyHandle,
+        collider: ColliderHandle,
+        stats: ShipStats,
+        flight: FlightParams,
+        fuel: f32,
+    ) -> Self {
+        Ship {
+            body,
+            collider,
+            stats,
+            flight,
+            move_vec: NVector2::zeros(),
+            rot: 0.0,
+            fuel,
+            tank: fuel,
+            rcs: stats.rcs_capacity,
+            heat: 0.0,
+            overheat_timer: 0.0,
+            boosting: false,
+            landed: None,
+            failed: false,
+        }
+    }
+
+    /// Thrust force in ship's local space
+    pub fn get_move_vec(&self) -> NVector2 {
+        self.move_vec
+    }
+
+    /// Main engine is on
+    pub fn is_thrusting(&self) -> bool {
+        self.move_vec.y < 0.
+    }
+
+    /// Afterburner is on
+    pub fn is_boosting(&self) -> bool {
+        self.boosting
+    }
+
+    pub fn is_overheated(&self) -> bool {
+        self.overheat_timer > 0.
+    }
+
+    /// Turns input into movement for the next physics step.
+    /// Fuel use is multiplied by `consumption` (nothing is burned at 0.0),
+    /// rotating only uses RCS fuel if it's `limited_rcs`
+    pub fn apply_input(
+        &mut self,
+        input: &ShipInput,
+        delta: f32,
+        consumption: f32,
+        limited_rcs: bool,
+    ) -> FuelUse {
+        let mut used = FuelUse::default();
+
+        // Movement
+        self.move_vec = vector![0., 0.];
+        self.boosting = false;
+
+        if self.fuel > 0. && !self.failed && !self.is_overheated() {
+            self.boosting = input.boost && input.forward > 0.;
+            let boost = if self.boosting { BOOST_MULTIPLIER } else { 1.0 };
+            let main = self.stats.main_thrust * input.forward * boost;
+            let reverse = self.stats.side_thrust * input.backward;
+            let left = self.stats.side_thrust * input.left;
+            let right = self.stats.side_thrust * input.right;
+            self.move_vec = vector![right - left, reverse - main];
+
+            // Fuel goes with the force the ship actually gets, so thrusters
+            // cancelling each other (e.g. hold velocity against the pilot) burn nothing
+            let fuel_per_force =
+                self.stats.burn_rate / self.stats.main_thrust * delta * consumption;
+            used.main = (main - reverse).max(0.) * fuel_per_force;
+            used.reverse = (reverse - main).max(0.) * fuel_per_force;
+            used.left = (left - right).max(0.) * fuel_per_force;
+            used.right = (right - left).max(0.) * fuel_per_force;
+            self.fuel -= used.engines();
+        }
+        self.fuel = self.fuel.max(0.);
+
+        // Rotating
+        self.rot = 0.0;
+        if self.rcs > 0. && !self.failed {
+            let rotation = input.rotate_right - input.rotate_left;
+            self.rot = self.stats.rotation_torque * rotation;
+            if limited_rcs {
+                used.rcs = rotation.abs() * self.stats.rcs_burn_rate * delta * consumption;
+                self.rcs = (self.rcs - used.rcs).max(0.);
+            }
+        }
+
+        used
+    }
+
+    /// Heats the ship up while boosting, cools it down by `cooling` per second otherwise
+    pub fn update_heat(&mut self, input: &ShipInput, delta: f32, cooling: f32) {
+        if self.is_overheated() {
+            self.overheat_timer = (self.overheat_timer - delta).max(0.);
+        }
+        if self.is_boosting() {
+            self.heat += delta * BOOST_HEAT * input.forward;
+        } else {
+            self.heat -= delta * cooling;
+        }
+        if self.heat >= 1.0 {
+            self.overheat_timer = OVERHEAT_COOLDOWN;
+        }
+        self.heat = self.heat.clamp(0., 1.);
+    }
+
+    pub fn physics_process(&self, delta: f32, body: &mut RigidBody) {
+        let force = *body.rotation() * self.move_vec;
+        body.apply_force(force, true);
+        body.apply_torque(self.rot, true);
+        self.flight.limit(delta, body);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ship(fuel: f32) -> Ship {
+        let stats = ShipStats::default();
+        Ship::new(
+            RigidBodyHandle::invalid(),
+            ColliderHandle::invalid(),
+            stats,
+            stats.flight_model.params(),
+            fuel,
+        )
+    }
+
+    #[test]
+    fn fuel_goes_with_applied_force() {
+        let mut ship = ship(100.0);
+        let input = ShipInput {
+            forward: 1.0,
+            ..ShipInput::default()
+        };
+        let used = ship.apply_input(&input, 1.0, 1.0, false);
+        assert!((used.main - ship.stats.burn_rate).abs() < 1e-4);
+        assert_eq!(used.engines(), used.main);
+        assert!((ship.fuel - (100.0 - ship.stats.burn_rate)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn opposite_thrusters_only_burn_for_the_net_force() {
+        let mut ship = ship(100.0);
+        // Side thrusters cancel out, reverse takes back part of the main engine's force
+        let input = ShipInput {
+            forward: 1.0,
+            backward: 1.0,
+            left: 1.0,
+            right: 1.0,
+            ..ShipInput::default()
+        };
+        let used = ship.apply_input(&input, 1.0, 1.0, false);
+        let stats = ship.stats;
+        let net = (stats.main_thrust - stats.side_thrust) / stats.main_thrust * stats.burn_rate;
+        assert!((used.main - net).abs() < 1e-4);
+        assert_eq!((used.reverse, used.left, used.right), (0.0, 0.0, 0.0));
+        assert_eq!(
+            ship.get_move_vec(),
+            vector![0.0, stats.side_thrust - stats.main_thrust]
+        );
+    }
+}
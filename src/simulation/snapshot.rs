@@ -0,0 +1,142 @@
+use super::Simulation;
+
+use serde::{Deserialize, Serialize};
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+
+/// Full state of a simulation (physics world, RNG, score, fuel, timers...).
+/// Restoring it continues the simulation exactly as the original would go on
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    sim: Simulation,
+}
+
+impl Snapshot {
+    pub fn time_since_start(&self) -> f32 {
+        self.sim.time_since_start
+    }
+
+    /// Snapshot was taken on the level `sim` is on (same seed, length
+    /// and ruleset) with the same ship
+    pub fn fits(&self, sim: &Simulation) -> bool {
+        let ship_stats = |sim: &Simulation| sim.ship.as_ref().map(|ship| ship.stats);
+        self.sim.seed == sim.seed
+            && self.sim.gate_count == sim.gate_count
+            && self.sim.ruleset == sim.ruleset
+            && ship_stats(&self.sim) == ship_stats(sim)
+    }
+
+    pub fn to_bytes(&self) -> bincode::Result<Vec<u8>> {
+        bincode::serialize(self)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> bincode::Result<Self> {
+        bincode::deserialize(bytes)
+    }
+
+    pub fn save(&self, path: &str) -> bincode::Result<()> {
+        let writer = BufWriter::new(File::create(path)?);
+        bincode::serialize_into(writer, self)
+    }
+
+    pub fn load(path: &str) -> bincode::Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        bincode::deserialize_from(reader)
+    }
+}
+
+impl Simulation {
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot { sim: self.clone() }
+    }
+
+    /// Puts the simulation back into the state from the snapshot
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        *self = snapshot.sim.clone();
+    }
+
+    pub fn from_snapshot(snapshot: Snapshot) -> Self {
+        snapshot.sim
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::ruleset::Ruleset;
+    use crate::simulation::{ShipInput, ShipStats};
+
+    fn level() -> Simulation {
+        Simulation::new_level(3, Ruleset::classic(), 3, ShipStats::default())
+    }
+
+    fn fly(sim: &mut Simulation, steps: u32) {
+        let input = ShipInput {
+            forward: 1.0,
+            rotate_left: 0.3,
+            ..Default::default()
+        };
+        for _ in 0..steps {
+            sim.step(&input);
+        }
+    }
+
+    /// Steps, score, time and where every body is
+    fn state(sim: &Simulation) -> (u32, i32, f32, Vec<[f32; 3]>) {
+        let bodies = sim
+            .rigid_body_set
+            .iter()
+            .map(|(_, body)| {
+                let position = body.position();
+                let translation = position.translation.vector;
+                [translation.x, translation.y, position.rotation.angle()]
+            })
+            .collect();
+        (sim.steps, sim.score, sim.time_since_start, bodies)
+    }
+
+    #[test]
+    fn restored_snapshot_continues_like_the_original() {
+        let mut original = level();
+        fly(&mut original, 30);
+        let snapshot = original.snapshot();
+        fly(&mut original, 120);
+
+        let mut restored = level();
+        fly(&mut restored, 75);
+        restored.restore(&snapshot);
+        fly(&mut restored, 120);
+        assert_eq!(state(&original), state(&restored));
+    }
+
+    #[test]
+    fn saved_snapshot_loads_and_fits() {
+        let mut sim = level();
+        fly(&mut sim, 30);
+        let path = std::env::temp_dir().join("snapshot_round_trip.bin");
+        let path = path.to_str().unwrap();
+        sim.snapshot().save(path).unwrap();
+        let loaded = Snapshot::load(path).unwrap();
+        std::fs::remove_file(path).ok();
+
+        assert!(loaded.fits(&sim));
+        let loaded = Simulation::from_snapshot(loaded);
+        assert_eq!((loaded.steps, loaded.score), (sim.steps, sim.score));
+    }
+
+    #[test]
+    fn snapshot_doesnt_fit_other_levels_or_ships() {
+        let snapshot = level().snapshot();
+        let ship = ShipStats::default();
+        let heavier = ShipStats {
+            mass: ship.mass * 2.0,
+            ..ship
+        };
+        assert!(!snapshot.fits(&Simulation::new_level(4, Ruleset::classic(), 3, ship)));
+        assert!(!snapshot.fits(&Simulation::new_level(3, Ruleset::classic(), 5, ship)));
+        assert!(!snapshot.fits(&Simulation::new_level(3, Ruleset::fuel(), 3, ship)));
+        assert!(!snapshot.fits(&Simulation::new_level(3, Ruleset::classic(), 3, heavier)));
+    }
+}
@@ -0,0 +1,61 @@
+use super::{Assists, FuelUse};
+
+use serde::{Deserialize, Serialize};
+
+/// Things about a run worth showing at its end (times are in seconds)
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+pub struct RunStats {
+    pub rotation_damping_time: f32,
+    pub orientation_time: f32,
+    pub hold_velocity_time: f32,
+    /// Fuel burned by each thruster
+    pub fuel_used: FuelUse,
+    /// Fuel from canisters, stations and planets
+    pub fuel_collected: f32,
+    pub asteroids_shot: u32,
+}
+
+impl RunStats {
+    /// Counts `delta` for every assist that was on
+    pub fn record_assists(&mut self, assists: &Assists, delta: f32) {
+        if assists.rotation_damping {
+            self.rotation_damping_time += delta;
+        }
+        if assists.orientation.is_some() {
+            self.orientation_time += delta;
+        }
+        if assists.hold_velocity {
+            self.hold_velocity_time += delta;
+        }
+    }
+
+    /// Any assist was used at all
+    pub fn assisted(&self) -> bool {
+        self.rotation_damping_time > 0.0
+            || self.orientation_time > 0.0
+            || self.hold_velocity_time > 0.0
+    }
+
+    /// One line summary of assists used, e.g. for the end of level screen
+    pub fn assists_summary(&self) -> String {
+        if !self.assisted() {
+            return "No assists used".to_string();
+        }
+        format!(
+            "Assists: damping {:.1}s, orientation {:.1}s, hold {:.1}s",
+            self.rotation_damping_time, self.orientation_time, self.hold_velocity_time
+        )
+    }
+
+    /// One line summary of fuel burned by each thruster
+    pub fn fuel_summary(&self) -> String {
+        let used = &self.fuel_used;
+        format!(
+            "Fuel: main {:.0}, reverse {:.0}, sides {:.0}, RCS {:.0}",
+            used.main,
+            used.reverse,
+            used.left + used.right,
+            used.rcs
+        )
+    }
+}
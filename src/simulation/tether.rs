@@ -0,0 +1,162 @@
+//! Grappling tether: a light rod joined to the ship on one end and to a planet
+//! or a big asteroid on the other, so the ship swings around it
+
+use super::{ShipInput, Simulation};
+
+use crate::math::{polygon, NPoint2, NVector2};
+
+use rapier2d::prelude::*;
+
+use serde::{Deserialize, Serialize};
+
+/// Tether reaches things this far from the ship (measured to their surface)
+const TETHER_RANGE: f32 = 700.0;
+/// Seconds after letting go before the tether can be fired again
+const TETHER_COOLDOWN: f32 = 1.5;
+/// Asteroids smaller than this would just be dragged along
+const MIN_ASTEROID_AREA: f32 = 6000.0;
+/// Mass of the rod (small, so it barely changes how the ship moves)
+const ROD_MASS: f32 = 40.0;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Tether {
+    /// Planet or asteroid the tether is attached to
+    pub target: RigidBodyHandle,
+    /// Where on the target (in its local space)
+    pub anchor: NPoint2,
+    /// Body between the target and the ship
+    rod: RigidBodyHandle,
+}
+
+impl Simulation {
+    pub fn tether(&self) -> Option<&Tether> {
+        self.tether.as_ref()
+    }
+
+    /// World position of the end attached to the target
+    /// (`None` if the target was destroyed, the tether is let go on the next step)
+    pub fn tether_anchor(&self, tether: &Tether) -> Option<NVector2> {
+        let target = self.rigid_body_set.get(tether.target)?;
+        Some((target.position() * tether.anchor).coords)
+    }
+
+    /// Seconds until the tether can be fired again
+    pub fn tether_cooldown(&self) -> f32 {
+        self.tether_cooldown
+    }
+
+    /// Attaches while `input.tether` is held (if there's something in range),
+    /// lets go when it's released or the target is gone
+    pub(super) fn update_tether(&mut self, input: &ShipInput, delta: f32) {
+        self.tether_cooldown = (self.tether_cooldown - delta).max(0.);
+
+        let can_hold = self
+            .ship
+            .as_ref()
+            .map(|ship| !ship.failed && ship.landed.is_none())
+            .unwrap_or(false);
+        if let Some(tether) = &self.tether {
+            let target_exists = self.is_planet(tether.target)
+                || self
+                    .asteroids
+                    .iter()
+                    .any(|(_, body)| *body == tether.target);
+            if !input.tether || !can_hold || !target_exists {
+                self.release_tether();
+            }
+        } else if input.tether && can_hold && self.tether_cooldown <= 0. {
+            if let Some((target, anchor)) = self.tether_target() {
+                self.attach_tether(target, anchor);
+            }
+        }
+    }
+
+    /// Closest planet or big asteroid in range and the point on it to attach to
+    /// (in world space)
+    fn tether_target(&self) -> Option<(RigidBodyHandle, NVector2)> {
+        let ship = self.ship.as_ref()?;
+        let position = *self.rigid_body_set[ship.body].translation();
+        let mut best: Option<(RigidBodyHandle, NVector2, f32)> = None;
+        let mut consider = |body: RigidBodyHandle, anchor: NVector2, distance: f32| {
+            let closer = best.map(|(_, _, d)| distance < d).unwrap_or(true);
+            if distance < TETHER_RANGE && closer {
+                best = Some((body, anchor, distance));
+            }
+        };
+
+        for planet in self.planets.iter() {
+            let center = *self.rigid_body_set[planet.body].translation();
+            let direction = match (position - center).try_normalize(0.01) {
+                Some(direction) => direction,
+                None => continue,
+            };
+            let surface = center + direction * planet.radius;
+            consider(planet.body, surface, (position - surface).norm());
+        }
+        for (col, body) in self.asteroids.iter() {
+            let big = self.collider_set[*col]
+                .shape()
+                .as_convex_polygon()
+                .map(|shape| polygon::area(shape.points()) > MIN_ASTEROID_AREA)
+                .unwrap_or(false);
+            if big {
+                let center = *self.rigid_body_set[*body].translation();
+                consider(*body, center, (position - center).norm());
+            }
+        }
+        best.map(|(body, anchor, _)| (body, anchor))
+    }
+
+    /// Joins the ship to `target` at `anchor` (world space) with a rod
+    fn attach_tether(&mut self, target: RigidBodyHandle, anchor: NVector2) {
+        let ship_body = match &self.ship {
+            Some(ship) => ship.body,
+            None => return,
+        };
+        let ship_position = *self.rigid_body_set[ship_body].translation();
+        let along = ship_position - anchor;
+        let length = along.norm().max(1.0);
+
+        // Rod lies between the anchor and the ship, its ends at -X and +X
+        let rod = RigidBodyBuilder::new_dynamic()
+            .translation((anchor + ship_position) * 0.5)
+            .rotation(along.y.atan2(along.x))
+            .additional_mass_properties(MassProperties::new(
+                point![0., 0.],
+                ROD_MASS,
+                ROD_MASS * length * length / 12.0,
+            ))
+            .linvel(*self.rigid_body_set[ship_body].linvel() * 0.5)
+            .build();
+        let rod = self.rigid_body_set.insert(rod);
+
+        let local_anchor = self.rigid_body_set[target]
+            .position()
+            .inverse_transform_point(&anchor.into());
+        let joints = &mut self.physics_server.joint_set;
+        joints.insert(
+            target,
+            rod,
+            BallJoint::new(local_anchor, point![-length / 2.0, 0.]),
+        );
+        joints.insert(
+            rod,
+            ship_body,
+            BallJoint::new(point![length / 2.0, 0.], point![0., 0.]),
+        );
+
+        self.tether = Some(Tether {
+            target,
+            anchor: local_anchor,
+            rod,
+        });
+    }
+
+    /// Lets go of the tether (removing the rod removes its joints too)
+    fn release_tether(&mut self) {
+        if let Some(tether) = self.tether.take() {
+            self.remove_rigidbody(tether.rod);
+            self.tether_cooldown = TETHER_COOLDOWN;
+        }
+    }
+}
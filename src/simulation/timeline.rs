@@ -0,0 +1,64 @@
+use super::{Replay, SimEvent, Simulation, Snapshot};
+
+/// Steps between two keyframes (seeking simulates at most this many)
+const KEYFRAME_INTERVAL: u32 = 180;
+
+/// Something that happened during a replay
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Marker {
+    /// Number of the gate passed
+    GatePassed(u32),
+    Collision,
+}
+
+/// Keyframes and markers of a whole replay, for jumping to any point of it
+pub struct Timeline {
+    /// State every `KEYFRAME_INTERVAL` steps from the start
+    keyframes: Vec<Snapshot>,
+    /// Step count right after each marked event
+    markers: Vec<(u32, Marker)>,
+}
+
+impl Timeline {
+    /// Simulates the whole replay once
+    pub fn new(replay: &Replay) -> Self {
+        let mut sim = replay.start();
+        let mut keyframes = Vec::new();
+        let mut markers = Vec::new();
+        while sim.steps() < replay.len() {
+            if sim.steps() % KEYFRAME_INTERVAL == 0 {
+                keyframes.push(sim.snapshot());
+            }
+            replay.step(&mut sim);
+            for event in sim.take_events() {
+                let marker = match event {
+                    SimEvent::GatePassed(gate) => Marker::GatePassed(gate),
+                    SimEvent::ShipImpact(_) | SimEvent::ShieldHit => Marker::Collision,
+                    SimEvent::Landed(_) => continue,
+                };
+                markers.push((sim.steps(), marker));
+            }
+        }
+        Timeline { keyframes, markers }
+    }
+
+    pub fn markers(&self) -> &[(u32, Marker)] {
+        &self.markers
+    }
+
+    /// Puts `sim` at `step` of the replay. Starts from the last keyframe before it
+    /// (or where `sim` already is, if that's closer)
+    pub fn seek(&self, replay: &Replay, sim: &mut Simulation, step: u32) {
+        let last = self.keyframes.len().saturating_sub(1);
+        let index = ((step / KEYFRAME_INTERVAL) as usize).min(last);
+        if let Some(keyframe) = self.keyframes.get(index) {
+            let keyframe_step = index as u32 * KEYFRAME_INTERVAL;
+            if sim.steps() > step || sim.steps() < keyframe_step {
+                sim.restore(keyframe);
+            }
+        }
+        while sim.steps() < step && replay.step(sim) {}
+        // Nothing of it should make sounds or save checkpoints
+        sim.take_events();
+    }
+}
@@ -0,0 +1,141 @@
+//! Ship's gun: shots are small fast bodies breaking asteroids they hit.
+//! Every shot heats the ship up (see `heat.rs`), so it can't fire forever
+
+use super::{CollisionGroup, ShipInput, Simulation, WithGroup};
+
+use rapier2d::prelude::*;
+
+use serde::{Deserialize, Serialize};
+
+/// Speed of shots (added to the ship's velocity)
+const SHOT_SPEED: f32 = 1800.0;
+const SHOT_RADIUS: f32 = 5.0;
+/// Shots disappear after this many seconds
+const SHOT_LIFETIME: f32 = 1.5;
+/// Seconds between shots while the trigger is held
+const FIRE_INTERVAL: f32 = 0.15;
+/// Heat every shot adds to the ship
+const SHOT_HEAT: f32 = 0.04;
+/// Points for every asteroid shot
+const ASTEROID_POINTS: i32 = 1;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Shot {
+    pub body: RigidBodyHandle,
+    pub collider: ColliderHandle,
+    /// Seconds since it was fired
+    age: f32,
+}
+
+impl Simulation {
+    pub fn shots(&self) -> &[Shot] {
+        &self.shots
+    }
+
+    pub(super) fn is_shot(&self, col: ColliderHandle) -> bool {
+        self.shots.iter().any(|shot| shot.collider == col)
+    }
+
+    /// Fires while `input.fire` is held (if the ruleset allows weapons),
+    /// ages shots and removes old ones and ones that hit a planet
+    pub(super) fn update_weapons(&mut self, input: &ShipInput, delta: f32) {
+        self.fire_cooldown = (self.fire_cooldown - delta).max(0.);
+
+        let expired: Vec<ColliderHandle> = self
+            .shots
+            .iter_mut()
+            .filter_map(|shot| {
+                shot.age += delta;
+                if shot.age > SHOT_LIFETIME {
+                    Some(shot.collider)
+                } else {
+                    None
+                }
+            })
+            .collect();
+        // Planets don't report touching shots (they don't push each other)
+        let in_planets: Vec<ColliderHandle> = self
+            .shots
+            .iter()
+            .filter(|shot| {
+                let position = self.rigid_body_set[shot.body].translation();
+                self.planets.iter().any(|planet| {
+                    let center = self.rigid_body_set[planet.body].translation();
+                    (position - center).norm() < planet.radius
+                })
+            })
+            .map(|shot| shot.collider)
+            .collect();
+        for col in expired.into_iter().chain(in_planets) {
+            self.remove_shot(col);
+        }
+
+        let can_fire = self
+            .ship
+            .as_ref()
+            .map(|ship| !ship.failed && !ship.is_overheated() && ship.landed.is_none())
+            .unwrap_or(false);
+        if input.fire && can_fire && self.ruleset.weapons && self.fire_cooldown <= 0. {
+            self.fire();
+            self.fire_cooldown = FIRE_INTERVAL;
+        }
+    }
+
+    /// Spawns a shot at the ship's nose
+    fn fire(&mut self) {
+        let (ship_body, nose_distance) = match self.ship.as_mut() {
+            Some(ship) => {
+                ship.heat = (ship.heat + SHOT_HEAT).min(1.0);
+                let shape = ship.stats.collider;
+                (ship.body, shape.half_height + shape.radius - shape.offset)
+            }
+            None => return,
+        };
+        let body = &self.rigid_body_set[ship_body];
+        let nose = body.rotation() * vector![0., -1.];
+        let position = body.translation() + nose * (nose_distance + SHOT_RADIUS * 2.0);
+        let linvel = body.linvel() + nose * SHOT_SPEED;
+
+        let rigid_body = RigidBodyBuilder::new_dynamic()
+            .translation(position)
+            .linvel(linvel)
+            .ccd_enabled(true)
+            .build();
+        let collider = ColliderBuilder::ball(SHOT_RADIUS)
+            .density(0.5)
+            .group(CollisionGroup::Projectile, &self.ruleset.collisions)
+            .active_events(ActiveEvents::CONTACT_EVENTS)
+            .build();
+        let body = self.rigid_body_set.insert(rigid_body);
+        let collider =
+            self.collider_set
+                .insert_with_parent(collider, body, &mut self.rigid_body_set);
+        self.shots.push(Shot {
+            body,
+            collider,
+            age: 0.,
+        });
+    }
+
+    fn remove_shot(&mut self, col: ColliderHandle) {
+        if let Some(index) = self.shots.iter().position(|shot| shot.collider == col) {
+            let shot = self.shots.remove(index);
+            self.remove_rigidbody(shot.body);
+        }
+    }
+
+    /// A shot touched something. Breaks it if it's an asteroid
+    pub(super) fn shot_hit(&mut self, shot: ColliderHandle, other: ColliderHandle) {
+        if !self.is_shot(shot) {
+            return;
+        }
+        self.remove_shot(shot);
+        if self.is_asteroid(other) {
+            self.remove_asteroid(other);
+            self.stats.asteroids_shot += 1;
+            if !self.completed {
+                self.score += ASTEROID_POINTS;
+            }
+        }
+    }
+}